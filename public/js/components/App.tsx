@@ -1,7 +1,14 @@
 import React, { useEffect, useState } from "react"
 import { BrowserRouter as Router, Routes, Route } from "react-router-dom"
 import { Chart, BarElement, CategoryScale, LinearScale } from "chart.js"
-import { Round } from "../data"
+import {
+  applyLiveUpdate,
+  CompactData,
+  expandCompactData,
+  LiveUpdate,
+  Round,
+  Server,
+} from "../data"
 import { Loading } from "./Loading"
 import { Main } from "./Main"
 
@@ -9,21 +16,68 @@ Chart.register(BarElement, CategoryScale, LinearScale)
 
 export const App = () => {
   const [rounds, setRounds] = useState<readonly Round[] | undefined>(undefined)
+  const [servers, setServers] = useState<readonly Server[] | undefined>(
+    undefined
+  )
   const [loadError, setLoadError] = useState<string | undefined>(undefined)
 
   useEffect(() => {
-    fetch("/data.json")
+    const loadRounds = () =>
+      fetch("/data.json?format=compact")
+        .then(async (response) => {
+          if (response.ok) {
+            const compactData: CompactData = await response.json()
+            const roundsData = expandCompactData(compactData)
+            roundsData.reverse()
+            return Object.freeze(roundsData)
+          } else {
+            throw new Error(response.statusText)
+          }
+        })
+        .then(setRounds)
+        .catch((error) => setLoadError(error.message))
+
+    loadRounds()
+
+    // Keeps a dashboard left open up to date, rather than waiting on a reload
+    const live = new EventSource("/api/live")
+
+    live.addEventListener("update", (event) => {
+      const update: LiveUpdate = JSON.parse((event as MessageEvent).data)
+
+      setRounds((rounds) => rounds && applyLiveUpdate(rounds, update))
+    })
+
+    // Updates were missed, so start over
+    live.addEventListener("lagged", loadRounds)
+
+    // Nothing is replayed after reconnecting, such as when the server restarts, so start over then too
+    let disconnected = false
+
+    live.addEventListener("error", () => {
+      disconnected = true
+    })
+
+    live.addEventListener("open", () => {
+      if (disconnected) {
+        disconnected = false
+        loadRounds()
+      }
+    })
+
+    fetch("/api/servers")
       .then(async (response) => {
         if (response.ok) {
-          const roundsData: Round[] = await response.json()
-          roundsData.reverse()
-          return Object.freeze(roundsData)
+          const serversData: Server[] = await response.json()
+          return Object.freeze(serversData)
         } else {
           throw new Error(response.statusText)
         }
       })
-      .then(setRounds)
+      .then(setServers)
       .catch((error) => setLoadError(error.message))
+
+    return () => live.close()
   }, [])
 
   return (
@@ -33,7 +87,11 @@ export const App = () => {
           <Route
             path="*"
             element={
-              rounds ? <Main rounds={rounds} /> : <Loading error={loadError} />
+              rounds && servers ? (
+                <Main rounds={rounds} servers={servers} />
+              ) : (
+                <Loading error={loadError} />
+              )
             }
           />
         </Routes>
@@ -1,24 +1,18 @@
 import React, { useState } from "react"
 import { Bar } from "react-chartjs-2"
-import { Round, RuntimeBatch } from "../data"
+import { Round, RuntimeBatch, Server } from "../data"
 import { RuntimeTable } from "./RuntimeTable"
 import { Route, Routes } from "react-router-dom"
 import { RuntimeViewer } from "./RuntimeViewer"
 
-const SERVER_COLORS: Record<string, string> = {
-  basil: "hsl(20, 100%, 15%)",
-  sybil: "hsl(245, 100%, 70%)",
-  manuel: "hsl(20, 100%, 50%)",
-  terry: "hsl(0, 100%, 30%)",
-  campbell: "hsl(275, 100%, 70%)",
-  "event-hall-us": "hsl(100, 100%, 70%)",
-  unknown: "hsl(0, 0%, 30%)",
-}
+const UNKNOWN_SERVER_COLOR = "hsl(0, 0%, 30%)"
 
 const ServerFilter = ({
+  servers,
   serverFilter,
   setServerFilter,
 }: {
+  servers: readonly Server[]
   serverFilter: string
   setServerFilter: (serverFilter: string) => void
 }) => {
@@ -29,13 +23,11 @@ const ServerFilter = ({
     >
       <option value="all">all servers</option>
 
-      {Object.keys(SERVER_COLORS)
-        .filter((server) => server !== "unknown")
-        .map((server) => (
-          <option key={server} value={server}>
-            {server}
-          </option>
-        ))}
+      {servers.map((server) => (
+        <option key={server.name} value={server.name}>
+          {server.name}
+        </option>
+      ))}
     </select>
   )
 }
@@ -59,7 +51,21 @@ const Timeframe = ({
   )
 }
 
-const RuntimeChart = ({ rounds }: { rounds: Round[] }) => {
+const RuntimeChart = ({
+  rounds,
+  servers,
+}: {
+  rounds: Round[]
+  servers: readonly Server[]
+}) => {
+  const serverColors: Record<string, string> = {}
+
+  for (const server of servers) {
+    if (server.color) {
+      serverColors[server.name] = server.color
+    }
+  }
+
   const labels = []
   const datasets: [string, number][] = []
 
@@ -87,7 +93,7 @@ const RuntimeChart = ({ rounds }: { rounds: Round[] }) => {
         datasets: [
           {
             backgroundColor: datasets.map(
-              ([server]) => SERVER_COLORS[server] || SERVER_COLORS.unknown
+              ([server]) => serverColors[server] || UNKNOWN_SERVER_COLOR
             ),
             data: datasets.map(([, count]) => count),
             barPercentage: 1,
@@ -110,7 +116,13 @@ const RuntimeChart = ({ rounds }: { rounds: Round[] }) => {
   )
 }
 
-export const Main = ({ rounds }: { rounds: readonly Round[] }) => {
+export const Main = ({
+  rounds,
+  servers,
+}: {
+  rounds: readonly Round[]
+  servers: readonly Server[]
+}) => {
   const [serverFilter, setServerFilter] = useState("all")
   const [timeframe, setTimeframe] = useState(7)
   const [collateSimilar, setCollateSimilar] = useState(true)
@@ -219,6 +231,7 @@ export const Main = ({ rounds }: { rounds: readonly Round[] }) => {
         }}
       >
         <ServerFilter
+          servers={servers}
           serverFilter={serverFilter}
           setServerFilter={setServerFilter}
         />
@@ -239,7 +252,7 @@ export const Main = ({ rounds }: { rounds: readonly Round[] }) => {
 
       <div>
         <div style={{ height: "18vh", width: "100%" }}>
-          <RuntimeChart rounds={filteredRounds} />
+          <RuntimeChart rounds={filteredRounds} servers={servers} />
         </div>
 
         <div
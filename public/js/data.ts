@@ -4,10 +4,35 @@ export type Round = {
   revision: string
   server: string
 
+  end_timestamp?: string
+  shutdown_timestamp?: string
+  duration_secs?: number
+  player_count?: number
+
+  map_name?: string
+  game_mode?: string
+  end_state?: string
+  station_name?: string
+
   runtimes?: RuntimeBatch[]
+  missing_runtimes?: MissingRuntimes
   test_merges: TestMerge[]
 }
 
+export type MissingRuntimes = {
+  reason: MissingRuntimesReason
+  attempts: number
+  last_attempt: string
+  next_attempt?: string
+  permanent: boolean
+}
+
+export type MissingRuntimesReason =
+  | { kind: "unknown_server"; details: number }
+  | { kind: "not_published" }
+  | { kind: "fetch_failed"; details: string }
+  | { kind: "parse_failed"; details: string }
+
 export type RuntimeBatch = {
   count: number
   exception: string
@@ -37,3 +62,64 @@ export type TestMergeDetails = {
   author: string
   commit: string
 }
+
+export type Server = {
+  name: string
+  port: number
+  color?: string
+  log_path?: string
+}
+
+// Sent by /data.json?format=compact, mirrors `CompactData` in dashboard.rs
+export type CompactData = {
+  runtimes: CompactRuntime[]
+  rounds: CompactRound[]
+}
+
+export type CompactRuntime = Omit<RuntimeBatch, "count">
+
+export type CompactRound = Omit<Round, "runtimes"> & {
+  // Pairs of an index into `CompactData.runtimes` and its count
+  runtimes?: [number, number][]
+}
+
+export const expandCompactData = (data: CompactData): Round[] => {
+  return data.rounds.map((round) => ({
+    ...round,
+    runtimes: round.runtimes?.map(([index, count]) => ({
+      ...data.runtimes[index],
+      count,
+    })),
+  }))
+}
+
+// Sent as `update` events from /api/live, mirrors `LiveUpdate` in live.rs
+export type LiveUpdate = {
+  rounds: Round[]
+  removed_round_ids: number[]
+}
+
+// Rounds are kept oldest first
+export const applyLiveUpdate = (
+  rounds: readonly Round[],
+  update: LiveUpdate
+): readonly Round[] => {
+  const removed = new Set(update.removed_round_ids)
+  const updated = new Map(update.rounds.map((round) => [round.round_id, round]))
+
+  const newRounds = rounds
+    .filter((round) => !removed.has(round.round_id))
+    .map((round) => updated.get(round.round_id) || round)
+
+  const existing = new Set(newRounds.map((round) => round.round_id))
+
+  for (const round of update.rounds) {
+    if (!existing.has(round.round_id)) {
+      newRounds.push(round)
+    }
+  }
+
+  newRounds.sort((a, b) => a.round_id - b.round_id)
+
+  return Object.freeze(newRounds)
+}
@@ -0,0 +1,195 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::{
+    analytics::{round_runtimes, RoundRuntime},
+    rounds::Round,
+};
+
+#[derive(Serialize)]
+pub struct RuntimesAggregate {
+    pub rounds: usize,
+
+    /// Total round time of every round with a known duration.
+    pub hours: f64,
+
+    /// Total players of every round with a known player count.
+    pub players: u64,
+
+    pub runtimes: Vec<RuntimeAggregate>,
+}
+
+#[derive(Serialize)]
+pub struct RuntimeAggregate {
+    pub fingerprint: String,
+    pub exception: String,
+    pub proc_path: String,
+    pub source_file: String,
+    pub line: u64,
+
+    pub count: u64,
+    pub rounds: usize,
+
+    /// Occurrences per hour of round time. Only counts rounds with a known duration.
+    pub per_hour: Option<f64>,
+
+    /// Occurrences per player. Only counts rounds with a known player count.
+    pub per_player: Option<f64>,
+
+    /// How many rounds on each map this runtime occurred in.
+    pub rounds_per_map: BTreeMap<String, usize>,
+}
+
+struct RuntimeAggregateBuilder {
+    aggregate: RuntimeAggregate,
+    count_with_duration: u64,
+    count_with_players: u64,
+}
+
+/// Totals every runtime over the given rounds, normalized by how long and how populated they were.
+pub fn aggregate_runtimes<'a>(rounds: impl IntoIterator<Item = &'a Round>) -> RuntimesAggregate {
+    let mut total_rounds = 0;
+    let mut total_hours = 0.0;
+    let mut total_players = 0;
+
+    let mut runtimes: HashMap<String, RuntimeAggregateBuilder> = HashMap::new();
+
+    for round in rounds {
+        if round.runtimes.is_none() {
+            continue;
+        }
+
+        total_rounds += 1;
+
+        if let Some(duration_secs) = round.duration_secs {
+            total_hours += duration_secs as f64 / 3600.0;
+        }
+
+        if let Some(player_count) = round.player_count {
+            total_players += player_count;
+        }
+
+        for RoundRuntime {
+            fingerprint,
+            runtime,
+            count,
+        } in round_runtimes(round)
+        {
+            let builder =
+                runtimes
+                    .entry(fingerprint.clone())
+                    .or_insert_with(|| RuntimeAggregateBuilder {
+                        aggregate: RuntimeAggregate {
+                            fingerprint,
+                            exception: runtime.exception.clone(),
+                            proc_path: runtime.proc_path.clone(),
+                            source_file: runtime.source_file.clone(),
+                            line: runtime.line,
+                            count: 0,
+                            rounds: 0,
+                            per_hour: None,
+                            per_player: None,
+                            rounds_per_map: BTreeMap::new(),
+                        },
+                        count_with_duration: 0,
+                        count_with_players: 0,
+                    });
+
+            builder.aggregate.count += count;
+            builder.aggregate.rounds += 1;
+
+            if round.duration_secs.is_some() {
+                builder.count_with_duration += count;
+            }
+
+            if round.player_count.is_some() {
+                builder.count_with_players += count;
+            }
+
+            if let Some(map_name) = &round.map_name {
+                *builder
+                    .aggregate
+                    .rounds_per_map
+                    .entry(map_name.clone())
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    let mut runtimes: Vec<RuntimeAggregate> = runtimes
+        .into_values()
+        .map(|builder| {
+            let mut aggregate = builder.aggregate;
+
+            if total_hours > 0.0 {
+                aggregate.per_hour = Some(builder.count_with_duration as f64 / total_hours);
+            }
+
+            if total_players > 0 {
+                aggregate.per_player =
+                    Some(builder.count_with_players as f64 / total_players as f64);
+            }
+
+            aggregate
+        })
+        .collect();
+
+    runtimes.sort_by_key(|runtime| std::cmp::Reverse(runtime.count));
+
+    RuntimesAggregate {
+        rounds: total_rounds,
+        hours: total_hours,
+        players: total_players,
+        runtimes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtimes::RuntimeBatch;
+
+    #[test]
+    fn test_aggregate_runtimes() {
+        let mut long_round = Round::mock(
+            1,
+            "2022-10-01 00:00:00",
+            vec![RuntimeBatch::mock("common", "/proc/common", 120)],
+        );
+        long_round.duration_secs = Some(3 * 3600);
+        long_round.player_count = Some(80);
+
+        let mut short_round = Round::mock(
+            2,
+            "2022-10-01 03:00:00",
+            vec![
+                RuntimeBatch::mock("common", "/proc/common", 40),
+                RuntimeBatch::mock("common", "/proc/common", 20),
+            ],
+        );
+        short_round.duration_secs = Some(3600);
+        short_round.player_count = Some(20);
+        short_round.map_name = Some("IceBoxStation".to_owned());
+
+        // Still going, so has no duration or player count
+        let current_round = Round::mock(
+            3,
+            "2022-10-01 04:00:00",
+            vec![RuntimeBatch::mock("common", "/proc/common", 1000)],
+        );
+
+        let aggregate = aggregate_runtimes(&[long_round, short_round, current_round]);
+
+        assert_eq!(aggregate.rounds, 3);
+        assert_eq!(aggregate.hours, 4.0);
+        assert_eq!(aggregate.players, 100);
+
+        let common = &aggregate.runtimes[0];
+        assert_eq!(common.count, 1180);
+        assert_eq!(common.rounds, 3);
+        assert_eq!(common.per_hour, Some(45.0));
+        assert_eq!(common.per_player, Some(1.8));
+        assert_eq!(common.rounds_per_map.get("IceBoxStation"), Some(&1));
+    }
+}
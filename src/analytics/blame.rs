@@ -0,0 +1,259 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ffi::OsStr,
+    path::PathBuf,
+};
+
+use serde::Serialize;
+
+use crate::{
+    rounds::{Round, TestMergeDetails},
+    runtimes::{BestGuessFilenames, RuntimeBatch},
+};
+
+/// How much more suspicious a test merge is when it changed the file the runtime is in.
+const TOUCHES_FILE_MULTIPLIER: f64 = 2.0;
+
+#[derive(Serialize)]
+pub struct RuntimeSuspects {
+    pub fingerprint: String,
+    pub exception: String,
+    pub proc_path: String,
+    pub suspects: Vec<SuspectTestMerge>,
+}
+
+#[derive(Serialize)]
+pub struct SuspectTestMerge {
+    pub details: TestMergeDetails,
+    pub score: f64,
+
+    /// Phi coefficient between "this PR was test merged" and "this runtime occurred" over every round.
+    pub correlation: f64,
+    pub touches_runtime_file: bool,
+
+    pub rounds_merged: usize,
+    pub rounds_merged_with_runtime: usize,
+    pub rounds_with_runtime: usize,
+}
+
+struct RuntimeInfo<'a> {
+    exception: &'a str,
+    proc_path: &'a str,
+    source_file: &'a str,
+    definitely: Option<&'a PathBuf>,
+    rounds: HashSet<usize>,
+}
+
+struct TestMergeInfo<'a> {
+    details: &'a TestMergeDetails,
+    files_changed: HashSet<&'a PathBuf>,
+    rounds: HashSet<usize>,
+}
+
+fn phi_coefficient(both: usize, only_a: usize, only_b: usize, neither: usize) -> f64 {
+    let (both, only_a, only_b, neither) =
+        (both as f64, only_a as f64, only_b as f64, neither as f64);
+
+    let denominator =
+        ((both + only_a) * (only_b + neither) * (both + only_b) * (only_a + neither)).sqrt();
+
+    if denominator == 0.0 {
+        0.0
+    } else {
+        (both * neither - only_a * only_b) / denominator
+    }
+}
+
+impl RuntimeInfo<'_> {
+    fn touched_by(&self, test_merge: &TestMergeInfo) -> bool {
+        test_merge
+            .files_changed
+            .iter()
+            .any(|file| match self.definitely {
+                Some(definitely) => *file == definitely,
+                None => file.file_name() == Some(OsStr::new(self.source_file)),
+            })
+    }
+}
+
+/// Ranks every test merge in the window by how likely it is to have caused each runtime.
+/// If `fingerprint` is given, only that runtime is scored.
+pub fn blame_test_merges<'a>(
+    rounds: impl IntoIterator<Item = &'a Round>,
+    fingerprint: Option<&str>,
+) -> Vec<RuntimeSuspects> {
+    let rounds: Vec<(&Round, &Vec<RuntimeBatch>)> = rounds
+        .into_iter()
+        .filter_map(|round| Some((round, round.runtimes.as_ref()?)))
+        .collect();
+
+    let mut runtimes: HashMap<String, RuntimeInfo> = HashMap::new();
+    let mut test_merges: BTreeMap<u64, TestMergeInfo> = BTreeMap::new();
+
+    for (index, (round, round_runtimes)) in rounds.iter().enumerate() {
+        for runtime in round_runtimes.iter() {
+            let runtime_fingerprint = runtime.fingerprint();
+
+            if matches!(fingerprint, Some(fingerprint) if fingerprint != runtime_fingerprint) {
+                continue;
+            }
+
+            let info = runtimes
+                .entry(runtime_fingerprint)
+                .or_insert_with(|| RuntimeInfo {
+                    exception: &runtime.exception,
+                    proc_path: &runtime.proc_path,
+                    source_file: &runtime.source_file,
+                    definitely: None,
+                    rounds: HashSet::new(),
+                });
+
+            if let Some(BestGuessFilenames::Definitely(filename)) = &runtime.best_guess_filenames {
+                info.definitely = Some(filename);
+            }
+
+            info.rounds.insert(index);
+        }
+
+        for test_merge in &round.test_merges {
+            let info = test_merges
+                .entry(test_merge.details.number)
+                .or_insert_with(|| TestMergeInfo {
+                    details: &test_merge.details,
+                    files_changed: HashSet::new(),
+                    rounds: HashSet::new(),
+                });
+
+            // Later commits of the same PR can change more files
+            if let Some(files_changed) = &test_merge.files_changed {
+                info.files_changed.extend(files_changed);
+            }
+
+            info.rounds.insert(index);
+        }
+    }
+
+    let total_rounds = rounds.len();
+
+    let mut results: Vec<RuntimeSuspects> = runtimes
+        .into_iter()
+        .map(|(fingerprint, runtime)| {
+            let mut suspects: Vec<SuspectTestMerge> = test_merges
+                .values()
+                .filter_map(|test_merge| {
+                    let both = test_merge.rounds.intersection(&runtime.rounds).count();
+                    let only_merged = test_merge.rounds.len() - both;
+                    let only_runtime = runtime.rounds.len() - both;
+                    let neither = total_rounds - both - only_merged - only_runtime;
+
+                    let correlation = phi_coefficient(both, only_merged, only_runtime, neither);
+
+                    // Negative correlation means the runtime happens *less* with this PR in
+                    if correlation <= 0.0 {
+                        return None;
+                    }
+
+                    let touches_runtime_file = runtime.touched_by(test_merge);
+
+                    Some(SuspectTestMerge {
+                        details: test_merge.details.clone(),
+                        score: if touches_runtime_file {
+                            correlation * TOUCHES_FILE_MULTIPLIER
+                        } else {
+                            correlation
+                        },
+                        correlation,
+                        touches_runtime_file,
+                        rounds_merged: test_merge.rounds.len(),
+                        rounds_merged_with_runtime: both,
+                        rounds_with_runtime: runtime.rounds.len(),
+                    })
+                })
+                .collect();
+
+            suspects.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+            RuntimeSuspects {
+                fingerprint,
+                exception: runtime.exception.to_owned(),
+                proc_path: runtime.proc_path.to_owned(),
+                suspects,
+            }
+        })
+        .collect();
+
+    results.sort_by(|a, b| {
+        let top_score = |suspects: &RuntimeSuspects| {
+            suspects
+                .suspects
+                .first()
+                .map_or(0.0, |suspect| suspect.score)
+        };
+
+        top_score(b).total_cmp(&top_score(a))
+    });
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rounds::{RoundId, TestMerge};
+
+    fn test_merge(number: u64, files_changed: &[&str]) -> TestMerge {
+        TestMerge {
+            details: TestMergeDetails {
+                number,
+                title: format!("PR {number}"),
+                author: "Mothblocks".to_owned(),
+                commit: format!("commit{number}"),
+            },
+            files_changed: Some(files_changed.iter().map(PathBuf::from).collect()),
+        }
+    }
+
+    #[test]
+    fn test_blame_test_merges() {
+        let rounds: Vec<Round> = (0..20)
+            .map(|round_id: RoundId| {
+                let culprit_merged = round_id % 2 == 0;
+                let mut runtimes = vec![RuntimeBatch::mock("everywhere", "/proc/everywhere", 1)];
+                let mut test_merges = vec![test_merge(3, &["code/unrelated.dm"])];
+
+                if culprit_merged {
+                    runtimes.push(RuntimeBatch::mock("broken", "/proc/broken", 5));
+                    test_merges.push(test_merge(1, &["code/modules/code.dm"]));
+                }
+
+                // Merged in a few of the same rounds as the culprit, but not all
+                if round_id % 4 == 0 {
+                    test_merges.push(test_merge(2, &["code/other.dm"]));
+                }
+
+                let mut round = Round::mock(round_id, "2022-10-01 00:00:00", runtimes);
+                round.test_merges = test_merges;
+                round
+            })
+            .collect();
+
+        let results = blame_test_merges(&rounds, Some("broken_______/proc/broken"));
+        assert_eq!(results.len(), 1);
+
+        let suspects = &results[0].suspects;
+        assert_eq!(suspects.len(), 2);
+
+        assert_eq!(suspects[0].details.number, 1);
+        assert!(suspects[0].touches_runtime_file);
+        assert_eq!(suspects[0].correlation, 1.0);
+        assert_eq!(suspects[0].rounds_merged_with_runtime, 10);
+
+        assert_eq!(suspects[1].details.number, 2);
+        assert!(!suspects[1].touches_runtime_file);
+        assert!(suspects[1].score < suspects[0].score);
+
+        // Merged every round, so it can't explain anything
+        let results = blame_test_merges(&rounds, Some("everywhere_______/proc/everywhere"));
+        assert!(results[0].suspects.is_empty());
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::{
+    analytics::{round_runtimes, RoundRuntime},
+    rounds::{Round, RoundId},
+};
+
+/// The `spike_ratio` used unless a webhook sets its own.
+pub const DEFAULT_SPIKE_RATIO: f64 = 3.0;
+
+/// How the runtimes changed between two loads of the rounds.
+#[derive(Default, Serialize)]
+pub struct RuntimeDiff {
+    /// Runtimes that weren't in any of the previous rounds.
+    pub new_runtimes: Vec<RuntimeChange>,
+
+    /// Runtimes happening more often per round in the newly loaded rounds than they used to.
+    pub spiking_runtimes: Vec<RuntimeSpike>,
+
+    /// Runtimes that were in the previous rounds on their latest revision, but aren't in any rounds anymore.
+    pub disappeared_runtimes: Vec<RuntimeChange>,
+}
+
+impl RuntimeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.new_runtimes.is_empty()
+            && self.spiking_runtimes.is_empty()
+            && self.disappeared_runtimes.is_empty()
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct RuntimeChange {
+    pub fingerprint: String,
+    pub exception: String,
+    pub proc_path: String,
+    pub source_file: String,
+    pub line: u64,
+
+    pub count: u64,
+    pub rounds: usize,
+}
+
+#[derive(Serialize)]
+pub struct RuntimeSpike {
+    /// Counts are for the newly loaded rounds only.
+    #[serde(flatten)]
+    pub runtime: RuntimeChange,
+
+    pub previous_per_round: f64,
+    pub current_per_round: f64,
+}
+
+/// Totals per runtime over the rounds that have runtimes, and how many rounds that was.
+fn totals<'a>(
+    rounds: impl IntoIterator<Item = &'a Round>,
+) -> (usize, HashMap<String, RuntimeChange>) {
+    let mut round_count = 0;
+    let mut totals: HashMap<String, RuntimeChange> = HashMap::new();
+
+    for round in rounds {
+        if round.runtimes.is_none() {
+            continue;
+        }
+
+        round_count += 1;
+
+        for RoundRuntime {
+            fingerprint,
+            runtime,
+            count,
+        } in round_runtimes(round)
+        {
+            let total = totals
+                .entry(fingerprint.clone())
+                .or_insert_with(|| RuntimeChange {
+                    fingerprint,
+                    exception: runtime.exception.clone(),
+                    proc_path: runtime.proc_path.clone(),
+                    source_file: runtime.source_file.clone(),
+                    line: runtime.line,
+                    count: 0,
+                    rounds: 0,
+                });
+
+            total.count += count;
+            total.rounds += 1;
+        }
+    }
+
+    (round_count, totals)
+}
+
+/// The revision of the latest round with runtimes.
+fn latest_revision(rounds: &[Round]) -> Option<&str> {
+    rounds
+        .iter()
+        .filter(|round| round.runtimes.is_some())
+        .max_by_key(|round| (round.timestamp, round.round_id))
+        .map(|round| round.revision.as_str())
+}
+
+fn sorted_by_count(mut runtimes: Vec<RuntimeChange>) -> Vec<RuntimeChange> {
+    runtimes.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.fingerprint.cmp(&b.fingerprint))
+    });
+
+    runtimes
+}
+
+/// Compares the rounds from before and after a refresh. A runtime is spiking when its rate per round
+/// in the rounds whose runtimes just loaded is at least `spike_ratio` times what it was before.
+pub fn diff_runtimes(previous: &[Round], current: &[Round], spike_ratio: f64) -> RuntimeDiff {
+    let (previous_round_count, previous_totals) = totals(previous);
+    let (_, current_totals) = totals(current);
+
+    let previously_loaded: HashSet<RoundId> = previous
+        .iter()
+        .filter(|round| round.runtimes.is_some())
+        .map(|round| round.round_id)
+        .collect();
+
+    let (newly_loaded_count, newly_loaded_totals) = totals(
+        current
+            .iter()
+            .filter(|round| !previously_loaded.contains(&round.round_id)),
+    );
+
+    let new_runtimes = current_totals
+        .values()
+        .filter(|runtime| !previous_totals.contains_key(&runtime.fingerprint))
+        .cloned()
+        .collect();
+
+    // Ones that only happened on older revisions have just aged out of the window, rather than stopped
+    let latest_revision = latest_revision(previous);
+    let (_, latest_revision_totals) = totals(
+        previous
+            .iter()
+            .filter(|round| Some(round.revision.as_str()) == latest_revision),
+    );
+
+    let disappeared_runtimes = latest_revision_totals
+        .into_values()
+        .filter(|runtime| !current_totals.contains_key(&runtime.fingerprint))
+        .collect();
+
+    let mut spiking_runtimes = Vec::new();
+
+    if newly_loaded_count > 0 && previous_round_count > 0 {
+        for runtime in newly_loaded_totals.into_values() {
+            // Runtimes that are entirely new are reported as such instead
+            let previous = match previous_totals.get(&runtime.fingerprint) {
+                Some(previous) => previous,
+                None => continue,
+            };
+
+            let previous_per_round = previous.count as f64 / previous_round_count as f64;
+            let current_per_round = runtime.count as f64 / newly_loaded_count as f64;
+
+            if current_per_round >= previous_per_round * spike_ratio {
+                spiking_runtimes.push(RuntimeSpike {
+                    runtime,
+                    previous_per_round,
+                    current_per_round,
+                });
+            }
+        }
+    }
+
+    spiking_runtimes.sort_by(|a, b| {
+        (b.current_per_round / b.previous_per_round)
+            .total_cmp(&(a.current_per_round / a.previous_per_round))
+            .then_with(|| a.runtime.fingerprint.cmp(&b.runtime.fingerprint))
+    });
+
+    RuntimeDiff {
+        new_runtimes: sorted_by_count(new_runtimes),
+        spiking_runtimes,
+        disappeared_runtimes: sorted_by_count(disappeared_runtimes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtimes::RuntimeBatch;
+
+    #[test]
+    fn test_diff_runtimes() {
+        let aged_out = Round::mock_with_revision(
+            0,
+            "2022-09-24 00:00:00",
+            "older",
+            vec![RuntimeBatch::mock("aged out", "/proc/e", 3)],
+        );
+
+        let previous = vec![
+            aged_out,
+            Round::mock(
+                1,
+                "2022-10-01 00:00:00",
+                vec![
+                    RuntimeBatch::mock("steady", "/proc/a", 10),
+                    RuntimeBatch::mock("spiking", "/proc/b", 2),
+                    RuntimeBatch::mock("going away", "/proc/c", 4),
+                ],
+            ),
+            Round::mock(
+                2,
+                "2022-10-01 01:00:00",
+                vec![
+                    RuntimeBatch::mock("steady", "/proc/a", 10),
+                    RuntimeBatch::mock("spiking", "/proc/b", 2),
+                ],
+            ),
+        ];
+
+        let current = vec![
+            Round::mock(
+                2,
+                "2022-10-01 01:00:00",
+                vec![
+                    RuntimeBatch::mock("steady", "/proc/a", 10),
+                    RuntimeBatch::mock("spiking", "/proc/b", 2),
+                ],
+            ),
+            Round::mock(
+                3,
+                "2022-10-01 02:00:00",
+                vec![
+                    RuntimeBatch::mock("steady", "/proc/a", 10),
+                    RuntimeBatch::mock("spiking", "/proc/b", 20),
+                    RuntimeBatch::mock("brand new", "/proc/d", 1),
+                ],
+            ),
+        ];
+
+        let diff = diff_runtimes(&previous, &current, 2.0);
+
+        let fingerprints = |runtimes: &[RuntimeChange]| -> Vec<String> {
+            runtimes
+                .iter()
+                .map(|runtime| runtime.exception.clone())
+                .collect()
+        };
+
+        assert_eq!(fingerprints(&diff.new_runtimes), vec!["brand new"]);
+        assert_eq!(fingerprints(&diff.disappeared_runtimes), vec!["going away"]);
+
+        assert_eq!(diff.spiking_runtimes.len(), 1);
+        let spike = &diff.spiking_runtimes[0];
+        assert_eq!(spike.runtime.exception, "spiking");
+        assert_eq!(spike.previous_per_round, 4.0 / 3.0);
+        assert_eq!(spike.current_per_round, 20.0);
+
+        // Nothing changes when nothing new was loaded
+        assert!(diff_runtimes(&current, &current, 2.0).is_empty());
+    }
+}
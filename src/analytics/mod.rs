@@ -0,0 +1,17 @@
+mod aggregate;
+pub use aggregate::*;
+
+mod blame;
+pub use blame::*;
+
+mod diff;
+pub use diff::*;
+
+mod revisions;
+pub use revisions::*;
+
+mod round_runtimes;
+pub use round_runtimes::*;
+
+mod trends;
+pub use trends::*;
@@ -0,0 +1,204 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use crate::{
+    analytics::{round_runtimes, RoundRuntime},
+    rounds::{Round, RoundId},
+};
+
+#[derive(Serialize)]
+pub struct RevisionRuntimes {
+    pub revision: String,
+    pub first_round_id: RoundId,
+    pub first_seen: NaiveDateTime,
+    pub rounds: usize,
+
+    /// The earliest revision in the window has nothing to compare against,
+    /// so every one of its runtimes is considered new.
+    pub is_baseline: bool,
+
+    pub new_runtimes: Vec<NewRuntime>,
+}
+
+#[derive(Serialize)]
+pub struct NewRuntime {
+    pub fingerprint: String,
+    pub exception: String,
+    pub proc_path: String,
+    pub source_file: String,
+    pub line: u64,
+
+    /// Totals for this revision only.
+    pub count: u64,
+    pub rounds: usize,
+}
+
+struct RevisionBuilder<'a> {
+    first_round: &'a Round,
+    rounds: usize,
+    runtimes: HashMap<String, NewRuntime>,
+}
+
+/// For every revision, finds the runtimes that didn't occur in any revision deployed before it.
+/// Returned newest revision first.
+pub fn new_runtimes_per_revision<'a>(
+    rounds: impl IntoIterator<Item = &'a Round>,
+) -> Vec<RevisionRuntimes> {
+    let mut revisions: HashMap<&str, RevisionBuilder> = HashMap::new();
+
+    for round in rounds {
+        if round.runtimes.is_none() {
+            continue;
+        }
+
+        let revision = revisions
+            .entry(&round.revision)
+            .or_insert_with(|| RevisionBuilder {
+                first_round: round,
+                rounds: 0,
+                runtimes: HashMap::new(),
+            });
+
+        if round.timestamp < revision.first_round.timestamp {
+            revision.first_round = round;
+        }
+
+        revision.rounds += 1;
+
+        for RoundRuntime {
+            fingerprint,
+            runtime,
+            count,
+        } in round_runtimes(round)
+        {
+            let new_runtime = revision
+                .runtimes
+                .entry(fingerprint.clone())
+                .or_insert_with(|| NewRuntime {
+                    fingerprint,
+                    exception: runtime.exception.clone(),
+                    proc_path: runtime.proc_path.clone(),
+                    source_file: runtime.source_file.clone(),
+                    line: runtime.line,
+                    count: 0,
+                    rounds: 0,
+                });
+
+            new_runtime.count += count;
+            new_runtime.rounds += 1;
+        }
+    }
+
+    let mut revisions: Vec<(&str, RevisionBuilder)> = revisions.into_iter().collect();
+
+    // Servers don't all update at once, so order by when the revision was first deployed anywhere
+    revisions.sort_by_key(|(_, revision)| {
+        (
+            revision.first_round.timestamp,
+            revision.first_round.round_id,
+        )
+    });
+
+    let mut seen_before: HashSet<String> = HashSet::new();
+    let mut results = Vec::with_capacity(revisions.len());
+
+    for (index, (revision_hash, revision)) in revisions.into_iter().enumerate() {
+        let mut new_runtimes = Vec::new();
+
+        for (fingerprint, runtime) in revision.runtimes {
+            if seen_before.contains(&fingerprint) {
+                continue;
+            }
+
+            seen_before.insert(fingerprint);
+            new_runtimes.push(runtime);
+        }
+
+        new_runtimes.sort_by_key(|runtime| std::cmp::Reverse(runtime.count));
+
+        results.push(RevisionRuntimes {
+            revision: revision_hash.to_owned(),
+            first_round_id: revision.first_round.round_id,
+            first_seen: revision.first_round.timestamp,
+            rounds: revision.rounds,
+            is_baseline: index == 0,
+            new_runtimes,
+        });
+    }
+
+    results.reverse();
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtimes::RuntimeBatch;
+
+    fn exceptions(revision: &RevisionRuntimes) -> Vec<&str> {
+        let mut exceptions: Vec<&str> = revision
+            .new_runtimes
+            .iter()
+            .map(|runtime| runtime.exception.as_str())
+            .collect();
+
+        exceptions.sort_unstable();
+        exceptions
+    }
+
+    #[test]
+    fn test_new_runtimes_per_revision() {
+        let rounds = vec![
+            Round::mock_with_revision(
+                1,
+                "2022-10-01 00:00:00",
+                "aaa",
+                RuntimeBatch::mock_each(&["old"]),
+            ),
+            Round::mock_with_revision(
+                2,
+                "2022-10-02 00:00:00",
+                "bbb",
+                RuntimeBatch::mock_each(&["old", "added in bbb"]),
+            ),
+            // Server still on the old revision after bbb went out
+            Round::mock_with_revision(
+                3,
+                "2022-10-02 01:00:00",
+                "aaa",
+                RuntimeBatch::mock_each(&["old", "late"]),
+            ),
+            Round::mock_with_revision(
+                4,
+                "2022-10-03 00:00:00",
+                "ccc",
+                RuntimeBatch::mock_each(&["late", "added in ccc"]),
+            ),
+            Round::mock_with_revision(
+                5,
+                "2022-10-03 01:00:00",
+                "ccc",
+                RuntimeBatch::mock_each(&["added in ccc"]),
+            ),
+        ];
+
+        let revisions = new_runtimes_per_revision(&rounds);
+
+        assert_eq!(revisions.len(), 3);
+
+        assert_eq!(revisions[0].revision, "ccc");
+        assert_eq!(exceptions(&revisions[0]), vec!["added in ccc"]);
+        assert_eq!(revisions[0].new_runtimes[0].rounds, 2);
+        assert!(!revisions[0].is_baseline);
+
+        assert_eq!(revisions[1].revision, "bbb");
+        assert_eq!(exceptions(&revisions[1]), vec!["added in bbb"]);
+
+        assert_eq!(revisions[2].revision, "aaa");
+        assert_eq!(exceptions(&revisions[2]), vec!["late", "old"]);
+        assert_eq!(revisions[2].rounds, 2);
+        assert!(revisions[2].is_baseline);
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use crate::{rounds::Round, runtimes::RuntimeBatch};
+
+/// Everything one round had of a runtime.
+pub struct RoundRuntime<'a> {
+    pub fingerprint: String,
+
+    /// The first batch with this fingerprint, for its details.
+    pub runtime: &'a RuntimeBatch,
+    pub count: u64,
+}
+
+/// Every runtime in the round once, in the order they first show up. The same fingerprint
+/// can be in more than one batch, from different lines, so their counts are added together.
+pub fn round_runtimes(round: &Round) -> Vec<RoundRuntime<'_>> {
+    let mut indexes: HashMap<String, usize> = HashMap::new();
+    let mut round_runtimes: Vec<RoundRuntime> = Vec::new();
+
+    for runtime in round.runtimes.iter().flatten() {
+        let fingerprint = runtime.fingerprint();
+
+        match indexes.get(&fingerprint) {
+            Some(&index) => round_runtimes[index].count += runtime.count,
+
+            None => {
+                indexes.insert(fingerprint.clone(), round_runtimes.len());
+                round_runtimes.push(RoundRuntime {
+                    fingerprint,
+                    runtime,
+                    count: runtime.count,
+                });
+            }
+        }
+    }
+
+    round_runtimes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_runtimes() {
+        let mut other_line = RuntimeBatch::mock("broken", "/proc/broken", 2);
+        other_line.line += 1;
+
+        let round = Round::mock(
+            1,
+            "2022-10-01 00:00:00",
+            vec![
+                RuntimeBatch::mock("broken", "/proc/broken", 3),
+                RuntimeBatch::mock("other", "/proc/other", 1),
+                other_line,
+            ],
+        );
+
+        let round_runtimes = round_runtimes(&round);
+
+        assert_eq!(
+            round_runtimes
+                .iter()
+                .map(|round_runtime| (round_runtime.fingerprint.as_str(), round_runtime.count))
+                .collect::<Vec<_>>(),
+            [
+                ("broken_______/proc/broken", 5),
+                ("other_______/proc/other", 1)
+            ]
+        );
+        assert_eq!(round_runtimes[0].runtime.count, 3);
+    }
+}
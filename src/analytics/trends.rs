@@ -0,0 +1,454 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Duration, NaiveDateTime};
+use serde::Serialize;
+
+use crate::{
+    analytics::{round_runtimes, RoundRuntime},
+    rounds::{Round, RoundId},
+};
+
+/// Minimum number of rounds on each side of the cutoff before a change can be called significant.
+const MIN_ROUNDS_PER_PERIOD: usize = 5;
+
+/// Welch's t statistic needed to call a change significant.
+/// Deliberately strict, since every runtime is tested at once and counts are very bursty.
+const SIGNIFICANT_T_STATISTIC: f64 = 3.0;
+
+#[derive(Serialize)]
+pub struct RuntimeTrend {
+    pub fingerprint: String,
+    pub exception: String,
+    pub proc_path: String,
+    pub total: u64,
+
+    /// Occurrences per hour of round time. Only counts rounds with a known duration.
+    pub per_hour: Option<f64>,
+
+    // Only contain entries where the runtime occurred
+    pub per_round: Vec<RoundOccurrences>,
+    pub per_day: Vec<BucketOccurrences>,
+
+    pub change: TrendChange,
+}
+
+#[derive(Serialize)]
+pub struct RoundOccurrences {
+    pub round_id: RoundId,
+    pub timestamp: NaiveDateTime,
+    pub count: u64,
+
+    /// Occurrences per hour of round time, if the round's duration is known.
+    pub per_hour: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct BucketOccurrences {
+    pub start: NaiveDateTime,
+    pub count: u64,
+
+    /// How many rounds started in this bucket, whether or not the runtime occurred in them.
+    pub rounds: usize,
+
+    /// Total round time of the rounds in this bucket with a known duration.
+    pub hours: f64,
+
+    /// Occurrences per hour of round time. Only counts rounds with a known duration.
+    pub per_hour: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct TrendChange {
+    pub direction: TrendDirection,
+
+    /// Mean occurrences per round before the recent period.
+    pub baseline_mean: f64,
+
+    /// Mean occurrences per round during the recent period.
+    pub recent_mean: f64,
+
+    /// None when there's not enough data, or when both periods have no variance.
+    pub t_statistic: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrendDirection {
+    Increasing,
+    Decreasing,
+    Stable,
+    InsufficientData,
+}
+
+#[derive(Default)]
+struct PeriodStats {
+    sum: f64,
+    sum_of_squares: f64,
+}
+
+impl PeriodStats {
+    fn add(&mut self, count: u64) {
+        let count = count as f64;
+        self.sum += count;
+        self.sum_of_squares += count * count;
+    }
+
+    /// Rounds where the runtime didn't occur are implicitly counted as zeroes.
+    fn mean_and_variance(&self, rounds: usize) -> (f64, f64) {
+        let rounds = rounds as f64;
+        let mean = self.sum / rounds;
+        let variance = (self.sum_of_squares - rounds * mean * mean).max(0.0) / (rounds - 1.0);
+
+        (mean, variance)
+    }
+}
+
+#[derive(Default)]
+struct Counts {
+    count: u64,
+    count_with_duration: u64,
+}
+
+impl Counts {
+    fn add(&mut self, count: u64, hours: Option<f64>) {
+        self.count += count;
+
+        if hours.is_some() {
+            self.count_with_duration += count;
+        }
+    }
+
+    fn per_hour(&self, hours: f64) -> Option<f64> {
+        (hours > 0.0).then(|| self.count_with_duration as f64 / hours)
+    }
+}
+
+#[derive(Default)]
+struct RoundTotals {
+    rounds: usize,
+    hours: f64,
+}
+
+impl RoundTotals {
+    fn add(&mut self, hours: Option<f64>) {
+        self.rounds += 1;
+        self.hours += hours.unwrap_or_default();
+    }
+}
+
+struct TrendBuilder {
+    exception: String,
+    proc_path: String,
+    total: Counts,
+
+    per_round: Vec<RoundOccurrences>,
+    per_day: BTreeMap<NaiveDateTime, Counts>,
+
+    baseline: PeriodStats,
+    recent: PeriodStats,
+}
+
+fn start_of_day(timestamp: NaiveDateTime) -> NaiveDateTime {
+    timestamp.date().and_hms_opt(0, 0, 0).expect("invalid day")
+}
+
+fn buckets(
+    counts: BTreeMap<NaiveDateTime, Counts>,
+    rounds: &BTreeMap<NaiveDateTime, RoundTotals>,
+) -> Vec<BucketOccurrences> {
+    counts
+        .into_iter()
+        .map(|(start, counts)| {
+            let RoundTotals { rounds, hours } = rounds[&start];
+
+            BucketOccurrences {
+                start,
+                count: counts.count,
+                rounds,
+                hours,
+                per_hour: counts.per_hour(hours),
+            }
+        })
+        .collect()
+}
+
+fn change(
+    baseline: &PeriodStats,
+    baseline_rounds: usize,
+    recent: &PeriodStats,
+    recent_rounds: usize,
+) -> TrendChange {
+    if baseline_rounds < MIN_ROUNDS_PER_PERIOD || recent_rounds < MIN_ROUNDS_PER_PERIOD {
+        return TrendChange {
+            direction: TrendDirection::InsufficientData,
+            baseline_mean: baseline.sum / baseline_rounds.max(1) as f64,
+            recent_mean: recent.sum / recent_rounds.max(1) as f64,
+            t_statistic: None,
+        };
+    }
+
+    let (baseline_mean, baseline_variance) = baseline.mean_and_variance(baseline_rounds);
+    let (recent_mean, recent_variance) = recent.mean_and_variance(recent_rounds);
+
+    let standard_error = (baseline_variance / baseline_rounds as f64
+        + recent_variance / recent_rounds as f64)
+        .sqrt();
+
+    let (direction, t_statistic) = if standard_error == 0.0 {
+        // Perfectly consistent on both sides, so any difference at all is real
+        let direction = if recent_mean > baseline_mean {
+            TrendDirection::Increasing
+        } else if recent_mean < baseline_mean {
+            TrendDirection::Decreasing
+        } else {
+            TrendDirection::Stable
+        };
+
+        (direction, None)
+    } else {
+        let t_statistic = (recent_mean - baseline_mean) / standard_error;
+
+        let direction = if t_statistic >= SIGNIFICANT_T_STATISTIC {
+            TrendDirection::Increasing
+        } else if t_statistic <= -SIGNIFICANT_T_STATISTIC {
+            TrendDirection::Decreasing
+        } else {
+            TrendDirection::Stable
+        };
+
+        (direction, Some(t_statistic))
+    };
+
+    TrendChange {
+        direction,
+        baseline_mean,
+        recent_mean,
+        t_statistic,
+    }
+}
+
+/// Builds a time series for every runtime in the given rounds, and compares the last `recent`
+/// stretch of time against everything before it.
+pub fn runtime_trends<'a>(
+    rounds: impl IntoIterator<Item = &'a Round>,
+    recent: Duration,
+) -> Vec<RuntimeTrend> {
+    let mut rounds: Vec<&Round> = rounds
+        .into_iter()
+        .filter(|round| round.runtimes.is_some())
+        .collect();
+
+    rounds.sort_by_key(|round| round.timestamp);
+
+    let cutoff = match rounds.last() {
+        Some(last_round) => last_round.timestamp - recent,
+        None => return Vec::new(),
+    };
+
+    let mut total_rounds = RoundTotals::default();
+    let mut rounds_per_day: BTreeMap<NaiveDateTime, RoundTotals> = BTreeMap::new();
+    let mut baseline_rounds = 0;
+    let mut recent_rounds = 0;
+
+    let mut trends: HashMap<String, TrendBuilder> = HashMap::new();
+
+    for round in rounds {
+        let day = start_of_day(round.timestamp);
+        let hours = round
+            .duration_secs
+            .map(|duration_secs| duration_secs as f64 / 3600.0);
+        let is_recent = round.timestamp > cutoff;
+
+        total_rounds.add(hours);
+        rounds_per_day.entry(day).or_default().add(hours);
+
+        if is_recent {
+            recent_rounds += 1;
+        } else {
+            baseline_rounds += 1;
+        }
+
+        for RoundRuntime {
+            fingerprint,
+            runtime,
+            count,
+        } in round_runtimes(round)
+        {
+            let trend = trends.entry(fingerprint).or_insert_with(|| TrendBuilder {
+                exception: runtime.exception.clone(),
+                proc_path: runtime.proc_path.clone(),
+                total: Counts::default(),
+                per_round: Vec::new(),
+                per_day: BTreeMap::new(),
+                baseline: PeriodStats::default(),
+                recent: PeriodStats::default(),
+            });
+
+            trend.total.add(count, hours);
+
+            trend.per_round.push(RoundOccurrences {
+                round_id: round.round_id,
+                timestamp: round.timestamp,
+                count,
+                per_hour: hours
+                    .filter(|hours| *hours > 0.0)
+                    .map(|hours| count as f64 / hours),
+            });
+
+            trend.per_day.entry(day).or_default().add(count, hours);
+
+            if is_recent {
+                trend.recent.add(count);
+            } else {
+                trend.baseline.add(count);
+            }
+        }
+    }
+
+    let mut trends: Vec<RuntimeTrend> = trends
+        .into_iter()
+        .map(|(fingerprint, trend)| RuntimeTrend {
+            change: change(
+                &trend.baseline,
+                baseline_rounds,
+                &trend.recent,
+                recent_rounds,
+            ),
+
+            fingerprint,
+            exception: trend.exception,
+            proc_path: trend.proc_path,
+            total: trend.total.count,
+            per_hour: trend.total.per_hour(total_rounds.hours),
+
+            per_round: trend.per_round,
+            per_day: buckets(trend.per_day, &rounds_per_day),
+        })
+        .collect();
+
+    trends.sort_by_key(|trend| std::cmp::Reverse(trend.total));
+
+    trends
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtimes::RuntimeBatch;
+
+    fn direction_of(trends: &[RuntimeTrend], exception: &str) -> TrendDirection {
+        trends
+            .iter()
+            .find(|trend| trend.exception == exception)
+            .unwrap()
+            .change
+            .direction
+    }
+
+    #[test]
+    fn test_runtime_trends() {
+        let mut rounds = Vec::new();
+
+        for day in 1..=6 {
+            for hour in [2, 10, 18] {
+                let count = (day + hour) as u64 % 3 + 1;
+
+                rounds.push(Round::mock(
+                    rounds.len() as RoundId,
+                    &format!("2022-10-{day:02} {hour:02}:00:00"),
+                    vec![
+                        RuntimeBatch::mock("rising", "/proc/rising", count),
+                        RuntimeBatch::mock("steady", "/proc/steady", 10 + count),
+                        RuntimeBatch::mock("fixed", "/proc/fixed", 50 + count),
+                    ],
+                ));
+            }
+        }
+
+        for hour in [1, 5, 9, 13, 17, 21] {
+            let count = hour as u64 % 3 + 1;
+
+            rounds.push(Round::mock(
+                rounds.len() as RoundId,
+                &format!("2022-10-07 {hour:02}:00:00"),
+                vec![
+                    RuntimeBatch::mock("rising", "/proc/rising", 100 + count),
+                    RuntimeBatch::mock("steady", "/proc/steady", 10 + count),
+                    // Same fingerprint, different line
+                    RuntimeBatch::mock("rising", "/proc/rising", 1),
+                ],
+            ));
+        }
+
+        let trends = runtime_trends(&rounds, Duration::hours(24));
+
+        assert_eq!(direction_of(&trends, "rising"), TrendDirection::Increasing);
+        assert_eq!(direction_of(&trends, "steady"), TrendDirection::Stable);
+        assert_eq!(direction_of(&trends, "fixed"), TrendDirection::Decreasing);
+
+        let rising = trends
+            .iter()
+            .find(|trend| trend.exception == "rising")
+            .unwrap();
+
+        assert_eq!(rising.per_round.len(), rounds.len());
+        assert_eq!(rising.per_day.len(), 7);
+        assert_eq!(rising.per_day.last().unwrap().rounds, 6);
+        assert_eq!(rising.per_hour, None);
+    }
+
+    #[test]
+    fn test_rates_per_hour_of_round_time() {
+        let mut long = Round::mock(
+            1,
+            "2022-10-01 00:00:00",
+            vec![RuntimeBatch::mock("common", "/proc/common", 6)],
+        );
+        long.duration_secs = Some(3 * 3600);
+
+        let mut short = Round::mock(
+            2,
+            "2022-10-01 04:00:00",
+            vec![RuntimeBatch::mock("common", "/proc/common", 1)],
+        );
+        short.duration_secs = Some(1800);
+
+        // Still running, so it doesn't count towards the rate
+        let unknown = Round::mock(
+            3,
+            "2022-10-01 05:00:00",
+            vec![RuntimeBatch::mock("common", "/proc/common", 100)],
+        );
+
+        let trends = runtime_trends(&[long, short, unknown], Duration::hours(24));
+        let common = &trends[0];
+
+        assert_eq!(common.per_hour, Some(7.0 / 3.5));
+        assert_eq!(
+            common
+                .per_round
+                .iter()
+                .map(|occurrences| occurrences.per_hour)
+                .collect::<Vec<_>>(),
+            [Some(2.0), Some(2.0), None]
+        );
+        assert_eq!(common.per_day[0].hours, 3.5);
+        assert_eq!(common.per_day[0].per_hour, Some(2.0));
+    }
+
+    #[test]
+    fn test_insufficient_data() {
+        let rounds = vec![Round::mock(
+            1,
+            "2022-10-01 00:00:00",
+            vec![RuntimeBatch::mock("lonely", "/proc/lonely", 1)],
+        )];
+
+        let trends = runtime_trends(&rounds, Duration::hours(24));
+
+        assert_eq!(
+            direction_of(&trends, "lonely"),
+            TrendDirection::InsufficientData
+        );
+    }
+}
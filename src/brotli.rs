@@ -0,0 +1,725 @@
+//! Just enough of a brotli encoder (RFC 7932) for response bodies: greedy LZ77 matches, with one
+//! set of prefix codes per meta-block, and no static dictionary or context modeling.
+//!
+//! It's hand written since no brotli crate could be added without registry access. The tests
+//! decode its output with the system's libbrotlidec, so that needs to be installed to run them.
+
+const WINDOW_BITS: u32 = 22;
+const MAX_DISTANCE: usize = (1 << WINDOW_BITS) - 16;
+
+// Prefix codes are per meta-block, so smaller ones follow the data more closely
+const META_BLOCK_SIZE: usize = 1 << 20;
+
+const MIN_MATCH: usize = 4;
+const HASH_BITS: u32 = 16;
+
+const MAX_CHAIN: usize = 32;
+
+const NO_POSITION: u32 = u32::MAX;
+
+const LITERAL_ALPHABET_BITS: u32 = 8;
+const COMMAND_ALPHABET_BITS: u32 = 10;
+const DISTANCE_ALPHABET_BITS: u32 = 6;
+
+/// (extra bits, offset) for each insert length code.
+const INSERT_LENGTH_CODES: [(u32, usize); 24] = [
+    (0, 0),
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (0, 5),
+    (1, 6),
+    (1, 8),
+    (2, 10),
+    (2, 14),
+    (3, 18),
+    (3, 26),
+    (4, 34),
+    (4, 50),
+    (5, 66),
+    (5, 98),
+    (6, 130),
+    (7, 194),
+    (8, 322),
+    (9, 578),
+    (10, 1090),
+    (12, 2114),
+    (14, 6210),
+    (24, 22594),
+];
+
+/// (extra bits, offset) for each copy length code.
+const COPY_LENGTH_CODES: [(u32, usize); 24] = [
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (0, 5),
+    (0, 6),
+    (0, 7),
+    (0, 8),
+    (0, 9),
+    (1, 10),
+    (1, 12),
+    (2, 14),
+    (2, 18),
+    (3, 22),
+    (3, 30),
+    (4, 38),
+    (4, 54),
+    (5, 70),
+    (5, 102),
+    (6, 134),
+    (7, 198),
+    (8, 326),
+    (9, 582),
+    (10, 1094),
+    (24, 2118),
+];
+
+/// The order code length code lengths are written in.
+const CODE_LENGTH_ORDER: [usize; 18] =
+    [1, 2, 3, 4, 0, 5, 17, 6, 16, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+/// (bits, value) of the fixed code for code length code lengths 0 to 5.
+const CODE_LENGTH_LENGTH_CODES: [(u32, u64); 6] = [(2, 0), (4, 7), (3, 3), (2, 2), (2, 1), (4, 15)];
+
+const REPEAT_ZERO: usize = 17;
+
+struct BitWriter {
+    output: Vec<u8>,
+    bits: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, count: u32, value: u64) {
+        self.bits |= value << self.count;
+        self.count += count;
+
+        while self.count >= 8 {
+            self.output.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.output.push(self.bits as u8);
+        }
+
+        self.output
+    }
+}
+
+struct PrefixCode {
+    lengths: Vec<u8>,
+
+    /// Bit reversed, since they're written starting from their first bit.
+    codes: Vec<u16>,
+
+    /// Set when fewer than two symbols are used, which are then coded in zero bits.
+    only_symbol: Option<usize>,
+}
+
+impl PrefixCode {
+    fn new(frequencies: &[u32], max_length: u8) -> Self {
+        let used: Vec<usize> = (0..frequencies.len())
+            .filter(|&symbol| frequencies[symbol] > 0)
+            .collect();
+
+        if used.len() < 2 {
+            return Self {
+                lengths: vec![0; frequencies.len()],
+                codes: vec![0; frequencies.len()],
+                only_symbol: Some(used.first().copied().unwrap_or_default()),
+            };
+        }
+
+        // Rare symbols are counted as more common until the tree is shallow enough
+        let mut minimum = 1;
+
+        let lengths = loop {
+            let lengths = huffman_lengths(frequencies, minimum);
+
+            if lengths.iter().all(|&length| length <= max_length) {
+                break lengths;
+            }
+
+            minimum *= 2;
+        };
+
+        let mut length_counts = [0u16; 16];
+        for &length in &lengths {
+            length_counts[length as usize] += 1;
+        }
+        length_counts[0] = 0;
+
+        let mut next_code = [0u16; 16];
+        for length in 1..16 {
+            next_code[length] = (next_code[length - 1] + length_counts[length - 1]) << 1;
+        }
+
+        let codes = lengths
+            .iter()
+            .map(|&length| {
+                if length == 0 {
+                    return 0;
+                }
+
+                let code = next_code[length as usize];
+                next_code[length as usize] += 1;
+                code.reverse_bits() >> (16 - length)
+            })
+            .collect();
+
+        Self {
+            lengths,
+            codes,
+            only_symbol: None,
+        }
+    }
+
+    fn write_symbol(&self, writer: &mut BitWriter, symbol: usize) {
+        writer.write(self.lengths[symbol].into(), self.codes[symbol].into());
+    }
+
+    fn write(&self, writer: &mut BitWriter, alphabet_bits: u32) {
+        if let Some(symbol) = self.only_symbol {
+            // A simple prefix code with one symbol
+            writer.write(2, 1);
+            writer.write(2, 0);
+            writer.write(alphabet_bits, symbol as u64);
+            return;
+        }
+
+        // Trailing zeros are left out, the reader stops once the code is complete
+        let last = self.lengths.iter().rposition(|&length| length > 0).unwrap();
+
+        // (code length symbol, extra bits)
+        let mut tokens = Vec::new();
+        let mut symbol = 0;
+
+        while symbol <= last {
+            let length = self.lengths[symbol];
+
+            if length > 0 {
+                tokens.push((length as usize, 0));
+                symbol += 1;
+                continue;
+            }
+
+            let zeros = self.lengths[symbol..]
+                .iter()
+                .take_while(|&&length| length == 0)
+                .count();
+
+            push_zero_run(&mut tokens, zeros);
+            symbol += zeros;
+        }
+
+        let mut frequencies = [0; 18];
+        for &(token, _) in &tokens {
+            frequencies[token] += 1;
+        }
+
+        let code_length_code = PrefixCode::new(&frequencies, 5);
+
+        // No code length code lengths skipped
+        writer.write(2, 0);
+
+        match code_length_code.only_symbol {
+            // Any length will do, as long as the rest are zero
+            Some(only_symbol) => {
+                for token in CODE_LENGTH_ORDER {
+                    let (bits, value) = CODE_LENGTH_LENGTH_CODES[usize::from(token == only_symbol)];
+                    writer.write(bits, value);
+                }
+            }
+
+            None => {
+                let mut space = 32;
+
+                for token in CODE_LENGTH_ORDER {
+                    let length = code_length_code.lengths[token];
+                    let (bits, value) = CODE_LENGTH_LENGTH_CODES[length as usize];
+                    writer.write(bits, value);
+
+                    if length > 0 {
+                        space -= 32 >> length;
+
+                        if space == 0 {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        for (token, extra) in tokens {
+            code_length_code.write_symbol(writer, token);
+
+            if token == REPEAT_ZERO {
+                writer.write(3, extra);
+            }
+        }
+    }
+}
+
+// Repeat codes in a row multiply rather than add
+fn push_zero_run(tokens: &mut Vec<(usize, u64)>, mut zeros: usize) {
+    if zeros == 11 {
+        tokens.push((0, 0));
+        zeros -= 1;
+    }
+
+    if zeros < 3 {
+        tokens.extend(std::iter::repeat_n((0, 0), zeros));
+        return;
+    }
+
+    let start = tokens.len();
+    zeros -= 3;
+
+    loop {
+        tokens.push((REPEAT_ZERO, (zeros & 7) as u64));
+        zeros >>= 3;
+
+        if zeros == 0 {
+            break;
+        }
+
+        zeros -= 1;
+    }
+
+    tokens[start..].reverse();
+}
+
+/// Code lengths of a Huffman code, counting every used symbol as at least `minimum`.
+fn huffman_lengths(frequencies: &[u32], minimum: u32) -> Vec<u8> {
+    use std::{cmp::Reverse, collections::BinaryHeap};
+
+    // Leaves first, then the nodes joining them
+    let mut parents: Vec<Option<usize>> = vec![None; frequencies.len()];
+    let mut heap = BinaryHeap::new();
+
+    for (symbol, &frequency) in frequencies.iter().enumerate() {
+        if frequency > 0 {
+            heap.push(Reverse((u64::from(frequency.max(minimum)), symbol)));
+        }
+    }
+
+    while heap.len() > 1 {
+        let Reverse((first_weight, first)) = heap.pop().unwrap();
+        let Reverse((second_weight, second)) = heap.pop().unwrap();
+
+        let node = parents.len();
+        parents.push(None);
+        parents[first] = Some(node);
+        parents[second] = Some(node);
+
+        heap.push(Reverse((first_weight + second_weight, node)));
+    }
+
+    // Parents always come after their children, so go backwards
+    let mut depths = vec![0u8; parents.len()];
+    for node in (0..parents.len()).rev() {
+        if let Some(parent) = parents[node] {
+            depths[node] = depths[parent] + 1;
+        }
+    }
+
+    depths.truncate(frequencies.len());
+    depths
+}
+
+/// A copy of 0 only happens at the end of a meta-block, where it's left out.
+struct Command {
+    insert: usize,
+    copy: usize,
+    distance: usize,
+}
+
+/// A copy of 0 gets the first code, since it isn't read anyway.
+fn length_code(codes: &[(u32, usize); 24], length: usize) -> usize {
+    codes
+        .iter()
+        .rposition(|&(_, offset)| offset <= length)
+        .unwrap_or_default()
+}
+
+/// Always one of the symbols that reads a distance, rather than reusing the last one.
+fn command_symbol(insert_code: usize, copy_code: usize) -> usize {
+    let base = match (insert_code >> 3, copy_code >> 3) {
+        (0, 0) => 128,
+        (0, 1) => 192,
+        (1, 0) => 256,
+        (1, 1) => 320,
+        (0, 2) => 384,
+        (2, 0) => 448,
+        (1, 2) => 512,
+        (2, 1) => 576,
+        _ => 640,
+    };
+
+    base + ((insert_code & 7) << 3) + (copy_code & 7)
+}
+
+/// (symbol, extra bit count, extra bits), with no direct distance codes or postfix bits.
+fn distance_code(distance: usize) -> (usize, u32, u64) {
+    let offset = distance + 3;
+    let extra_bits = offset.ilog2() - 1;
+    let high_bit = (offset >> extra_bits) & 1;
+
+    (
+        16 + 2 * (extra_bits as usize - 1) + high_bit,
+        extra_bits,
+        (offset - ((2 + high_bit) << extra_bits)) as u64,
+    )
+}
+
+struct MatchFinder<'a> {
+    data: &'a [u8],
+    head: Vec<u32>,
+    previous: Vec<u32>,
+}
+
+impl<'a> MatchFinder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            head: vec![NO_POSITION; 1 << HASH_BITS],
+            previous: vec![NO_POSITION; data.len()],
+        }
+    }
+
+    fn hash(&self, position: usize) -> usize {
+        let bytes: [u8; 4] = self.data[position..position + 4].try_into().unwrap();
+        (u32::from_le_bytes(bytes).wrapping_mul(0x1e35_a7bd) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, position: usize) {
+        if position + 4 <= self.data.len() {
+            let hash = self.hash(position);
+            self.previous[position] = self.head[hash];
+            self.head[hash] = position as u32;
+        }
+    }
+
+    /// (length, distance)
+    fn find(&self, position: usize, end: usize) -> Option<(usize, usize)> {
+        if position + MIN_MATCH > end {
+            return None;
+        }
+
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidate = self.head[self.hash(position)];
+
+        for _ in 0..MAX_CHAIN {
+            if candidate == NO_POSITION {
+                break;
+            }
+
+            let distance = position - candidate as usize;
+            if distance > MAX_DISTANCE {
+                break;
+            }
+
+            let length = self.data[candidate as usize..]
+                .iter()
+                .zip(&self.data[position..end])
+                .take_while(|(earlier, current)| earlier == current)
+                .count();
+
+            if length >= MIN_MATCH && best.is_none_or(|(best_length, _)| length > best_length) {
+                best = Some((length, distance));
+            }
+
+            candidate = self.previous[candidate as usize];
+        }
+
+        best
+    }
+}
+
+fn find_commands(finder: &mut MatchFinder, start: usize, end: usize) -> Vec<Command> {
+    let mut commands = Vec::new();
+    let mut literals_start = start;
+    let mut position = start;
+
+    while position < end {
+        let found = finder.find(position, end);
+        finder.insert(position);
+
+        match found {
+            Some((length, distance)) => {
+                commands.push(Command {
+                    insert: position - literals_start,
+                    copy: length,
+                    distance,
+                });
+
+                for matched in position + 1..position + length {
+                    finder.insert(matched);
+                }
+
+                position += length;
+                literals_start = position;
+            }
+
+            None => position += 1,
+        }
+    }
+
+    if literals_start < end {
+        commands.push(Command {
+            insert: end - literals_start,
+            copy: 0,
+            distance: 0,
+        });
+    }
+
+    commands
+}
+
+fn write_meta_block(
+    writer: &mut BitWriter,
+    data: &[u8],
+    start: usize,
+    commands: &[Command],
+    is_last: bool,
+) {
+    let length = commands
+        .iter()
+        .map(|command| command.insert + command.copy)
+        .sum::<usize>();
+
+    writer.write(1, is_last.into());
+    if is_last {
+        // Not empty
+        writer.write(1, 0);
+    }
+
+    let nibbles = match length - 1 {
+        0..=0xffff => 4,
+        0x10000..=0xfffff => 5,
+        _ => 6,
+    };
+    writer.write(2, nibbles - 4);
+    writer.write(nibbles as u32 * 4, (length - 1) as u64);
+
+    if !is_last {
+        // Compressed
+        writer.write(1, 0);
+    }
+
+    // One block type each for literals, commands and distances
+    writer.write(3, 0);
+
+    // No postfix bits or direct distance codes
+    writer.write(2, 0);
+    writer.write(4, 0);
+
+    // One literal context mode, then one prefix code each for literals and distances
+    writer.write(2, 0);
+    writer.write(2, 0);
+
+    let mut literal_frequencies = [0; 256];
+    let mut command_frequencies = [0; 704];
+    let mut distance_frequencies = [0; 64];
+    let mut position = start;
+
+    for command in commands {
+        for &literal in &data[position..position + command.insert] {
+            literal_frequencies[literal as usize] += 1;
+        }
+
+        command_frequencies[command_symbol(
+            length_code(&INSERT_LENGTH_CODES, command.insert),
+            length_code(&COPY_LENGTH_CODES, command.copy),
+        )] += 1;
+
+        if command.copy > 0 {
+            distance_frequencies[distance_code(command.distance).0] += 1;
+        }
+
+        position += command.insert + command.copy;
+    }
+
+    let literal_code = PrefixCode::new(&literal_frequencies, 15);
+    let command_code = PrefixCode::new(&command_frequencies, 15);
+    let distance_code_code = PrefixCode::new(&distance_frequencies, 15);
+
+    literal_code.write(writer, LITERAL_ALPHABET_BITS);
+    command_code.write(writer, COMMAND_ALPHABET_BITS);
+    distance_code_code.write(writer, DISTANCE_ALPHABET_BITS);
+
+    let mut position = start;
+
+    for command in commands {
+        let insert_code = length_code(&INSERT_LENGTH_CODES, command.insert);
+        let copy_code = length_code(&COPY_LENGTH_CODES, command.copy);
+        command_code.write_symbol(writer, command_symbol(insert_code, copy_code));
+
+        let (insert_bits, insert_offset) = INSERT_LENGTH_CODES[insert_code];
+        writer.write(insert_bits, (command.insert - insert_offset) as u64);
+
+        let (copy_bits, copy_offset) = COPY_LENGTH_CODES[copy_code];
+        writer.write(copy_bits, command.copy.saturating_sub(copy_offset) as u64);
+
+        for &literal in &data[position..position + command.insert] {
+            literal_code.write_symbol(writer, literal as usize);
+        }
+
+        if command.copy > 0 {
+            let (symbol, extra_bits, extra) = distance_code(command.distance);
+            distance_code_code.write_symbol(writer, symbol);
+            writer.write(extra_bits, extra);
+        }
+
+        position += command.insert + command.copy;
+    }
+}
+
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter {
+        output: Vec::new(),
+        bits: 0,
+        count: 0,
+    };
+
+    writer.write(1, 1);
+    writer.write(3, (WINDOW_BITS - 17).into());
+
+    if data.is_empty() {
+        // Last, and empty
+        writer.write(2, 0b11);
+        return writer.finish();
+    }
+
+    let mut finder = MatchFinder::new(data);
+    let mut start = 0;
+
+    while start < data.len() {
+        let end = (start + META_BLOCK_SIZE).min(data.len());
+        let commands = find_commands(&mut finder, start, end);
+        write_meta_block(&mut writer, data, start, &commands, end == data.len());
+        start = end;
+    }
+
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[link(name = "brotlidec")]
+    extern "C" {
+        fn BrotliDecoderDecompress(
+            encoded_size: usize,
+            encoded_buffer: *const u8,
+            decoded_size: *mut usize,
+            decoded_buffer: *mut u8,
+        ) -> std::os::raw::c_int;
+    }
+
+    fn decompress(encoded: &[u8], size: usize) -> Vec<u8> {
+        // One spare byte, so output that's too long doesn't fit either
+        let mut decoded = vec![0; size + 1];
+        let mut decoded_size = decoded.len();
+
+        let result = unsafe {
+            BrotliDecoderDecompress(
+                encoded.len(),
+                encoded.as_ptr(),
+                &mut decoded_size,
+                decoded.as_mut_ptr(),
+            )
+        };
+
+        assert_eq!(result, 1, "libbrotlidec couldn't decode it");
+        decoded.truncate(decoded_size);
+        decoded
+    }
+
+    #[test]
+    fn test_distance_code() {
+        for distance in [1, 2, 3, 4, 5, 100, 65535, MAX_DISTANCE] {
+            let (symbol, extra_bits, extra) = distance_code(distance);
+
+            // Reading it back, as in section 4 of RFC 7932
+            let code = symbol - 16;
+            assert_eq!(extra_bits as usize, 1 + (code >> 1));
+            let offset = ((2 + (code & 1)) << extra_bits) - 4;
+            assert_eq!(offset + extra as usize + 1, distance);
+        }
+    }
+
+    #[test]
+    fn test_push_zero_run() {
+        for zeros in 1..500 {
+            let mut tokens = Vec::new();
+            push_zero_run(&mut tokens, zeros);
+
+            // Repeat codes in a row build on each other
+            let mut total = 0;
+            let mut repeat = 0;
+
+            for (token, extra) in tokens {
+                if token == REPEAT_ZERO {
+                    let previous = repeat;
+                    repeat =
+                        if previous > 0 { (previous - 2) << 3 } else { 0 } + 3 + extra as usize;
+                    total += repeat - previous;
+                } else {
+                    assert_eq!(token, 0);
+                    repeat = 0;
+                    total += 1;
+                }
+            }
+
+            assert_eq!(total, zeros);
+        }
+    }
+
+    #[test]
+    fn test_compress() {
+        // 22 bit window, then an empty last meta-block
+        assert_eq!(compress(b""), [0x3b]);
+
+        let data = "[{\"round_id\":1}]".repeat(1000);
+        assert!(compress(data.as_bytes()).len() < 100);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // xorshift, for bytes that don't compress
+        let mut state: u32 = 0x1234_5678;
+        let random: Vec<u8> = (0..200_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+
+        let json: String = (0..5000)
+            .map(|round_id| format!("{{\"round_id\":{round_id},\"server\":\"moth\"}},"))
+            .collect();
+
+        let inputs: [&[u8]; 5] = [
+            b"",
+            b"[{\"round_id\":1}]",
+            json.as_bytes(),
+            &random,
+            &vec![b'a'; 3 * META_BLOCK_SIZE + 123],
+        ];
+
+        for input in inputs {
+            assert_eq!(decompress(&compress(input), input.len()), input);
+        }
+    }
+}
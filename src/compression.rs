@@ -0,0 +1,181 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use axum::body::Bytes;
+use crc::{Crc, CRC_32_ISO_HDLC};
+
+// Slower is fine, bodies are compressed once and served many times
+const GZIP_LEVEL: u8 = 6;
+
+static CRC_32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Identity,
+    Gzip,
+    Brotli,
+}
+
+impl Encoding {
+    pub fn header_value(self) -> Option<&'static str> {
+        match self {
+            Encoding::Identity => None,
+            Encoding::Gzip => Some("gzip"),
+            Encoding::Brotli => Some("br"),
+        }
+    }
+}
+
+/// From `Accept-Encoding`.
+pub fn preferred_encoding(accept_encoding: Option<&str>) -> Encoding {
+    let accept_encoding = match accept_encoding {
+        Some(accept_encoding) => accept_encoding,
+        None => return Encoding::Identity,
+    };
+
+    let qualities: Vec<(&str, f32)> = accept_encoding
+        .split(',')
+        .map(|coding| {
+            let mut parts = coding.split(';');
+            let name = parts.next().unwrap_or_default().trim();
+
+            let quality = parts
+                .filter_map(|parameter| parameter.trim().strip_prefix("q="))
+                .find_map(|quality| quality.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            (name, quality)
+        })
+        .collect();
+
+    // `*` only covers codings that aren't named
+    let quality = |name: &str| {
+        qualities
+            .iter()
+            .find(|(coding, _)| coding.eq_ignore_ascii_case(name))
+            .or_else(|| qualities.iter().find(|(coding, _)| *coding == "*"))
+            .map_or(0.0, |(_, quality)| *quality)
+    };
+
+    let mut best = (Encoding::Identity, 0.0);
+
+    for encoding in [Encoding::Brotli, Encoding::Gzip] {
+        let encoding_quality = quality(encoding.header_value().unwrap());
+
+        if encoding_quality > best.1 {
+            best = (encoding, encoding_quality);
+        }
+    }
+
+    best.0
+}
+
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    // No modification time, so the same data always compresses the same
+    let mut output = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255];
+
+    output.extend(miniz_oxide::deflate::compress_to_vec(data, GZIP_LEVEL));
+    output.extend(CRC_32.checksum(data).to_le_bytes());
+    output.extend((data.len() as u32).to_le_bytes());
+
+    output
+}
+
+/// A response body, compressed once rather than per request.
+pub struct CompressedBody {
+    pub etag: String,
+
+    identity: Bytes,
+    gzip: Bytes,
+    brotli: Bytes,
+}
+
+impl CompressedBody {
+    /// Slow for big bodies, so run it off the async threads.
+    pub fn new(identity: Vec<u8>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        identity.hash(&mut hasher);
+
+        Self {
+            etag: format!("W/\"{:016x}\"", hasher.finish()),
+            gzip: gzip(&identity).into(),
+            brotli: crate::brotli::compress(&identity).into(),
+            identity: identity.into(),
+        }
+    }
+
+    pub fn encoded(&self, encoding: Encoding) -> Bytes {
+        match encoding {
+            Encoding::Identity => self.identity.clone(),
+            Encoding::Gzip => self.gzip.clone(),
+            Encoding::Brotli => self.brotli.clone(),
+        }
+    }
+
+    pub fn matches(&self, if_none_match: &str) -> bool {
+        if_none_match.split(',').any(|etag| {
+            let etag = etag.trim();
+            etag == "*" || etag.trim_start_matches("W/") == self.etag.trim_start_matches("W/")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preferred_encoding() {
+        assert_eq!(preferred_encoding(None), Encoding::Identity);
+        assert_eq!(
+            preferred_encoding(Some("gzip, deflate, br")),
+            Encoding::Brotli
+        );
+        assert_eq!(preferred_encoding(Some("gzip, deflate")), Encoding::Gzip);
+        assert_eq!(preferred_encoding(Some("br;q=0.5, gzip")), Encoding::Gzip);
+        assert_eq!(preferred_encoding(Some("br;q=0, *;q=0.5")), Encoding::Gzip);
+        assert_eq!(
+            preferred_encoding(Some("gzip;q=0, identity")),
+            Encoding::Identity
+        );
+        assert_eq!(
+            preferred_encoding(Some("gzip;q=0, br;q=0, *")),
+            Encoding::Identity
+        );
+        assert_eq!(preferred_encoding(Some("gzip;q=0, *")), Encoding::Brotli);
+        assert_eq!(preferred_encoding(Some("deflate")), Encoding::Identity);
+    }
+
+    #[test]
+    fn test_gzip() {
+        let data = "[{\"round_id\":1}]".repeat(100);
+        let compressed = gzip(data.as_bytes());
+
+        assert!(compressed.len() < data.len());
+        assert_eq!(&compressed[..3], &[0x1f, 0x8b, 8]);
+
+        let (deflated, trailer) = compressed[10..].split_at(compressed.len() - 18);
+        assert_eq!(
+            miniz_oxide::inflate::decompress_to_vec(deflated).unwrap(),
+            data.as_bytes()
+        );
+        assert_eq!(trailer[..4], CRC_32.checksum(data.as_bytes()).to_le_bytes());
+        assert_eq!(trailer[4..], (data.len() as u32).to_le_bytes());
+    }
+
+    #[test]
+    fn test_compressed_body_matches() {
+        let body = CompressedBody::new(b"[]".to_vec());
+
+        assert!(body.matches(&body.etag));
+        assert!(body.matches(&format!(
+            "\"other\", {}",
+            body.etag.trim_start_matches("W/")
+        )));
+        assert!(body.matches("*"));
+        assert!(!body.matches("\"other\""));
+        assert_ne!(body.etag, CompressedBody::new(b"[1]".to_vec()).etag);
+    }
+}
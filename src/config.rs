@@ -1,22 +1,565 @@
-use std::{net::IpAddr, path::PathBuf};
+use std::{
+    collections::HashSet,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
+use color_eyre::eyre::{Context, ContextCompat};
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::Deserialize;
 
+use crate::{
+    file_cache::CACHE_PATH,
+    log_source::{HttpLogSource, LocalLogSource, LogSource},
+    servers::Server,
+    webhooks::WebhookConfig,
+};
+
+/// Name of the project made from the top level config when `projects` is empty.
+pub const DEFAULT_PROJECT_NAME: &str = "tgstation";
+
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+/// Environment variables starting with this override config keys.
+/// `__` separates nested keys, and numbers index into arrays, such as `MOTH_FANS_RUNTIMES_PROJECTS__0__DB_URL`.
+const ENV_PREFIX: &str = "MOTH_FANS_RUNTIMES_";
+
+/// Where to read the config from, if not passed with `--config`.
+const CONFIG_PATH_ENV: &str = "MOTH_FANS_RUNTIMES_CONFIG";
+
 #[derive(Clone, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
     pub address: IpAddr,
     pub port: u16,
 
-    pub db_url: String,
-
+    // Only used when `projects` is empty, for a single tgstation project
+    pub db_url: Option<String>,
+    pub db_url_file: Option<PathBuf>,
+    #[serde(default)]
+    pub database: DatabaseConfig,
     pub mock_runtimes_data: Option<PathBuf>,
+    pub logs_directory: Option<String>,
+    #[serde(default = "crate::servers::default_servers")]
+    pub servers: Vec<Server>,
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    pub github_token: Option<String>,
 
     pub rounds_cache_delay_secs: u64,
+
+    /// Bearer token for the `/api/admin` routes, which are disabled without one.
+    pub admin_token: Option<String>,
+
+    /// Where this is served publicly, such as `https://runtimes.moth.fans`, for links in the feed.
+    pub public_url: Option<String>,
+
+    #[serde(default)]
+    pub projects: Vec<ProjectConfig>,
 }
 
 impl Config {
-    pub fn read_from_file() -> color_eyre::Result<Self> {
-        toml::from_str(&std::fs::read_to_string("config.toml")?).map_err(Into::into)
+    /// Reads the config from `path` (or `config.toml`), then applies overrides from the environment.
+    pub fn read(path: Option<&Path>) -> color_eyre::Result<Self> {
+        let path = path
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::var_os(CONFIG_PATH_ENV).map(PathBuf::from));
+
+        let value = match &path {
+            Some(path) => read_toml(path)?,
+
+            // Everything can come from the environment, so the default file is optional
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                read_toml(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => toml::Value::Table(toml::value::Table::new()),
+        };
+
+        let mut config = parse_config_with_env_overrides(value, std::env::vars())?;
+
+        config.db_url = resolve_db_url(config.db_url.take(), config.db_url_file.take())
+            .context("invalid `db_url`")?;
+
+        for (index, project) in config.projects.iter_mut().enumerate() {
+            project.db_url = resolve_db_url(project.db_url.take(), project.db_url_file.take())
+                .with_context(|| format!("invalid `projects.{index}.db_url`"))?;
+        }
+
+        // Make sure the projects are valid before starting anything
+        config.projects()?;
+
+        Ok(config)
+    }
+
+    /// The configured projects, or a single tgstation project from the top level fields if there are none.
+    pub fn projects(&self) -> color_eyre::Result<Vec<ProjectConfig>> {
+        if !self.projects.is_empty() {
+            let mut names = HashSet::new();
+
+            for project in &self.projects {
+                if !names.insert(&project.name) {
+                    color_eyre::eyre::bail!("duplicate project name `{}`", project.name);
+                }
+            }
+
+            return Ok(self.projects.clone());
+        }
+
+        Ok(vec![ProjectConfig {
+            name: DEFAULT_PROJECT_NAME.to_owned(),
+            github_repository: default_github_repository(),
+            logs_url: default_logs_url(),
+            logs_directory: self.logs_directory.clone(),
+            db_url: self.db_url.clone(),
+            db_url_file: None,
+            database: self.database.clone(),
+            mock_runtimes_data: self.mock_runtimes_data.clone(),
+            servers: self.servers.clone(),
+            webhooks: self.webhooks.clone(),
+            github_token: self.github_token.clone(),
+            dashboard_url: None,
+        }])
+    }
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Used in API paths and cache folders.
+    pub name: String,
+
+    #[serde(default = "default_github_repository")]
+    pub github_repository: String,
+
+    /// Where to find a round's logs. `{server}`, `{year}`, `{month}`, `{day}` and `{round_id}` are filled in.
+    #[serde(default = "default_logs_url")]
+    pub logs_url: String,
+
+    /// If set, logs are read from `<logs_directory>/data/logs` on disk instead of `logs_url`.
+    /// `{server}` is filled in.
+    pub logs_directory: Option<String>,
+
+    /// If unset, rounds are found by listing the days' log folders instead.
+    /// Only rounds from the last week are looked at either way.
+    pub db_url: Option<String>,
+
+    /// Reads `db_url` from this file instead, to keep it out of the config.
+    pub db_url_file: Option<PathBuf>,
+
+    #[serde(default)]
+    pub database: DatabaseConfig,
+
+    pub mock_runtimes_data: Option<PathBuf>,
+
+    #[serde(default = "crate::servers::default_servers")]
+    pub servers: Vec<Server>,
+
+    /// Told about new, spiking and disappeared runtimes after every refresh.
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+
+    /// Lets admins file drafted issues straight onto `github_repository`.
+    pub github_token: Option<String>,
+
+    /// Where this project's dashboard is, for links to its runtimes.
+    /// Defaults to `public_url` for the first project, since that's the one served at the root.
+    pub dashboard_url: Option<String>,
+}
+
+/// Takes the config path from `--config <path>` or `--config=<path>`.
+pub fn config_path_from_args(
+    mut args: impl Iterator<Item = String>,
+) -> color_eyre::Result<Option<PathBuf>> {
+    let mut path = None;
+
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            path = Some(PathBuf::from(
+                args.next().context("`--config` needs a path")?,
+            ));
+        } else if let Some(value) = arg.strip_prefix("--config=") {
+            path = Some(PathBuf::from(value));
+        } else {
+            color_eyre::eyre::bail!("unknown argument `{arg}`, expected `--config <path>`");
+        }
+    }
+
+    Ok(path)
+}
+
+fn read_toml(path: &Path) -> color_eyre::Result<toml::Value> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("couldn't read `{}`", path.display()))?;
+
+    toml::from_str(&contents).with_context(|| format!("couldn't parse `{}`", path.display()))
+}
+
+/// Deserializes through TOML text rather than the value directly, since only then do errors name the key.
+fn parse_config(value: toml::Value) -> color_eyre::Result<Config> {
+    Ok(toml::from_str(&toml::to_string(&value)?)?)
+}
+
+/// Applies `MOTH_FANS_RUNTIMES_*` variables to `value` and parses it.
+/// Values read as something else that turn out to be for a string, such as a numeric token, are retried as strings.
+fn parse_config_with_env_overrides(
+    value: toml::Value,
+    vars: impl Iterator<Item = (String, String)>,
+) -> color_eyre::Result<Config> {
+    static TYPE_ERROR_KEY_REGEX: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^invalid type: .* for key `(?P<key>[^`]+)`").unwrap());
+
+    let vars: Vec<(String, String)> = vars.collect();
+    let mut as_strings = HashSet::new();
+
+    loop {
+        let mut overridden_value = value.clone();
+        let overridden =
+            apply_env_overrides(&mut overridden_value, vars.iter().cloned(), &as_strings)?;
+
+        let error = match parse_config(overridden_value) {
+            Ok(config) => return Ok(config),
+            Err(error) => error,
+        };
+
+        // Array indexes aren't part of the key in the error
+        let error_string = error.to_string();
+        let key = TYPE_ERROR_KEY_REGEX
+            .captures(&error_string)
+            .map(|captures| captures["key"].to_owned());
+
+        let retry: Vec<String> = vars
+            .iter()
+            .filter(|(name, raw_value)| {
+                overridden.contains(name)
+                    && !as_strings.contains(name)
+                    && !matches!(parse_env_value(raw_value), toml::Value::String(_))
+                    && key.as_deref() == Some(&env_key_path(name))
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        if retry.is_empty() {
+            return Err(error).with_context(|| {
+                if overridden.is_empty() {
+                    "invalid config".to_owned()
+                } else {
+                    format!(
+                        "invalid config, after overriding from {}",
+                        overridden.join(", ")
+                    )
+                }
+            });
+        }
+
+        as_strings.extend(retry);
+    }
+}
+
+/// The key a variable overrides as it appears in errors, such as `projects.db_url`.
+fn env_key_path(name: &str) -> String {
+    name.trim_start_matches(ENV_PREFIX)
+        .to_lowercase()
+        .split("__")
+        .filter(|segment| segment.parse::<usize>().is_err())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Overrides keys in `value` with `MOTH_FANS_RUNTIMES_*` variables, returning the ones that were used.
+/// Values are read as TOML when they can be, so `8` is a number and `[...]` an array, and as strings otherwise.
+/// The ones in `as_strings` are always strings.
+fn apply_env_overrides(
+    value: &mut toml::Value,
+    vars: impl Iterator<Item = (String, String)>,
+    as_strings: &HashSet<String>,
+) -> color_eyre::Result<Vec<String>> {
+    let mut overridden = Vec::new();
+
+    for (name, raw_value) in vars {
+        let key = match name.strip_prefix(ENV_PREFIX) {
+            Some(key) if name != CONFIG_PATH_ENV => key.to_lowercase(),
+            _ => continue,
+        };
+
+        let new_value = if as_strings.contains(&name) {
+            toml::Value::String(raw_value)
+        } else {
+            parse_env_value(&raw_value)
+        };
+
+        let mut target = &mut *value;
+        let mut segments = key.split("__").peekable();
+
+        while let Some(segment) = segments.next() {
+            let is_last = segments.peek().is_none();
+
+            target = match target {
+                toml::Value::Table(table) => {
+                    if is_last {
+                        table.insert(segment.to_owned(), new_value);
+                        break;
+                    }
+
+                    table
+                        .entry(segment.to_owned())
+                        .or_insert_with(|| toml::Value::Table(toml::value::Table::new()))
+                }
+
+                toml::Value::Array(array) => {
+                    let index: usize = segment
+                        .parse()
+                        .ok()
+                        .filter(|index| *index < array.len())
+                        .with_context(|| {
+                            format!("`{name}` has `{segment}`, which isn't an existing index")
+                        })?;
+
+                    if is_last {
+                        array[index] = new_value;
+                        break;
+                    }
+
+                    &mut array[index]
+                }
+
+                _ => color_eyre::eyre::bail!("`{name}` goes inside a value that isn't a table"),
+            };
+        }
+
+        overridden.push(name);
+    }
+
+    overridden.sort();
+
+    Ok(overridden)
+}
+
+fn parse_env_value(raw_value: &str) -> toml::Value {
+    #[derive(Deserialize)]
+    struct Wrapper {
+        value: toml::Value,
+    }
+
+    match toml::from_str::<Wrapper>(&format!("value = {raw_value}")) {
+        Ok(wrapper) => wrapper.value,
+        Err(_) => toml::Value::String(raw_value.to_owned()),
+    }
+}
+
+fn resolve_db_url(
+    db_url: Option<String>,
+    db_url_file: Option<PathBuf>,
+) -> color_eyre::Result<Option<String>> {
+    match (db_url, db_url_file) {
+        (Some(_), Some(_)) => {
+            color_eyre::eyre::bail!("only one of `db_url` and `db_url_file` can be set")
+        }
+
+        (None, Some(path)) => Ok(Some(
+            std::fs::read_to_string(&path)
+                .with_context(|| format!("couldn't read `db_url_file` `{}`", path.display()))?
+                .trim()
+                .to_owned(),
+        )),
+
+        (db_url, None) => Ok(db_url),
+    }
+}
+
+/// Connection pool settings, under `[database]`.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub max_connections: u32,
+
+    /// How long to wait for a connection before giving up.
+    pub acquire_timeout_secs: u64,
+
+    /// How long an unused connection is kept open.
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 4,
+            acquire_timeout_secs: 30,
+            idle_timeout_secs: 600,
+        }
+    }
+}
+
+fn default_github_repository() -> String {
+    "tgstation/tgstation".to_owned()
+}
+
+fn default_logs_url() -> String {
+    "https://tgstation13.org/parsed-logs/{server}/data/logs/{year}/{month}/{day}/round-{round_id}"
+        .to_owned()
+}
+
+impl ProjectConfig {
+    pub fn cache_path(&self, path: &str) -> PathBuf {
+        PathBuf::from(CACHE_PATH).join(&self.name).join(path)
+    }
+
+    /// Kept out of the cache, since it can't be made again.
+    pub fn triage_path(&self) -> PathBuf {
+        PathBuf::from("triage").join(format!("{}.json", self.name))
+    }
+
+    pub fn log_source(&self) -> Arc<dyn LogSource> {
+        match &self.logs_directory {
+            Some(directory) => Arc::new(LocalLogSource {
+                directory: directory.clone(),
+            }),
+
+            None => Arc::new(HttpLogSource {
+                url: self.logs_url.clone(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let mut value: toml::Value = toml::from_str(
+            r#"
+            address = "127.0.0.1"
+            port = 2223
+            rounds_cache_delay_secs = 600
+
+            [[projects]]
+            name = "tgstation"
+            db_url = "mysql://plaintext"
+            "#,
+        )
+        .unwrap();
+
+        let overridden = apply_env_overrides(
+            &mut value,
+            vars(&[
+                ("MOTH_FANS_RUNTIMES_PORT", "80"),
+                ("MOTH_FANS_RUNTIMES_ADDRESS", "0.0.0.0"),
+                ("MOTH_FANS_RUNTIMES_DATABASE__MAX_CONNECTIONS", "8"),
+                ("MOTH_FANS_RUNTIMES_PROJECTS__0__DB_URL", "mysql://secret"),
+                ("MOTH_FANS_RUNTIMES_CONFIG", "elsewhere.toml"),
+                ("PATH", "/usr/bin"),
+            ]),
+            &HashSet::new(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            overridden,
+            vec![
+                "MOTH_FANS_RUNTIMES_ADDRESS",
+                "MOTH_FANS_RUNTIMES_DATABASE__MAX_CONNECTIONS",
+                "MOTH_FANS_RUNTIMES_PORT",
+                "MOTH_FANS_RUNTIMES_PROJECTS__0__DB_URL",
+            ]
+        );
+
+        let config = parse_config(value).unwrap();
+
+        assert_eq!(config.port, 80);
+        assert_eq!(config.address.to_string(), "0.0.0.0");
+        assert_eq!(config.database.max_connections, 8);
+        assert_eq!(config.projects[0].db_url.as_deref(), Some("mysql://secret"));
+
+        assert!(apply_env_overrides(
+            &mut toml::from_str("projects = []").unwrap(),
+            vars(&[("MOTH_FANS_RUNTIMES_PROJECTS__0__DB_URL", "mysql://secret")]),
+            &HashSet::new(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_env_overrides_for_strings() {
+        let value: toml::Value = toml::from_str(
+            r#"
+            address = "127.0.0.1"
+            port = 2223
+            rounds_cache_delay_secs = 600
+
+            [[projects]]
+            name = "tgstation"
+            "#,
+        )
+        .unwrap();
+
+        let config = parse_config_with_env_overrides(
+            value,
+            vars(&[
+                ("MOTH_FANS_RUNTIMES_PORT", "80"),
+                ("MOTH_FANS_RUNTIMES_ADMIN_TOKEN", "12345"),
+                ("MOTH_FANS_RUNTIMES_PROJECTS__0__GITHUB_TOKEN", "true"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(config.port, 80);
+        assert_eq!(config.admin_token.as_deref(), Some("12345"));
+        assert_eq!(config.projects[0].github_token.as_deref(), Some("true"));
+    }
+
+    #[test]
+    fn test_errors_name_key() {
+        let mut value: toml::Value = toml::from_str(
+            r#"
+            address = "127.0.0.1"
+            port = 2223
+            rounds_cache_delay_secs = 600
+            "#,
+        )
+        .unwrap();
+
+        apply_env_overrides(
+            &mut value,
+            vars(&[("MOTH_FANS_RUNTIMES_DATABASE__MAX_CONNECTIONS", "lots")]),
+            &HashSet::new(),
+        )
+        .unwrap();
+
+        let error = parse_config(value).err().unwrap().to_string();
+        assert!(error.contains("max_connections"), "{error}");
+    }
+
+    #[test]
+    fn test_config_path_from_args() {
+        let args = |args: &[&str]| {
+            config_path_from_args(
+                args.iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            )
+        };
+
+        assert_eq!(args(&[]).unwrap(), None);
+        assert_eq!(
+            args(&["--config", "a.toml"]).unwrap(),
+            Some(PathBuf::from("a.toml"))
+        );
+        assert_eq!(
+            args(&["--config=b.toml"]).unwrap(),
+            Some(PathBuf::from("b.toml"))
+        );
+        assert!(args(&["--config"]).is_err());
+        assert!(args(&["--verbose"]).is_err());
     }
 }
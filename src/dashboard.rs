@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    missing_runtimes::MissingRuntimes,
+    rounds::{Round, RoundId, TestMerge},
+    runtimes::{BestGuessFilenames, RuntimeBatch},
+};
+
+/// Formats `/data.json` can be sent in, picked with `?format=`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DataFormat {
+    #[default]
+    Full,
+
+    Compact,
+}
+
+/// Every distinct runtime sent once, rather than repeated in each round. Mirrored on the frontend.
+#[derive(Serialize)]
+pub struct CompactData<'a> {
+    pub runtimes: Vec<CompactRuntime<'a>>,
+    pub rounds: Vec<CompactRound<'a>>,
+}
+
+#[derive(Serialize, PartialEq, Eq, Hash)]
+pub struct CompactRuntime<'a> {
+    pub exception: &'a str,
+    pub proc_path: &'a str,
+    pub source_file: &'a str,
+    pub line: u64,
+    pub best_guess_filenames: &'a Option<BestGuessFilenames>,
+}
+
+impl<'a> From<&'a RuntimeBatch> for CompactRuntime<'a> {
+    fn from(runtime: &'a RuntimeBatch) -> Self {
+        Self {
+            exception: &runtime.exception,
+            proc_path: &runtime.proc_path,
+            source_file: &runtime.source_file,
+            line: runtime.line,
+            best_guess_filenames: &runtime.best_guess_filenames,
+        }
+    }
+}
+
+/// Runtimes are (index into `runtimes`, count) pairs.
+#[derive(Serialize)]
+pub struct CompactRound<'a> {
+    pub round_id: RoundId,
+    pub timestamp: NaiveDateTime,
+    pub revision: &'a str,
+    pub server: &'a str,
+
+    pub end_timestamp: Option<NaiveDateTime>,
+    pub shutdown_timestamp: Option<NaiveDateTime>,
+    pub duration_secs: Option<u64>,
+    pub player_count: Option<u64>,
+
+    pub map_name: Option<&'a str>,
+    pub game_mode: Option<&'a str>,
+    pub end_state: Option<&'a str>,
+    pub station_name: Option<&'a str>,
+
+    pub runtimes: Option<Vec<(usize, u64)>>,
+    pub missing_runtimes: &'a Option<MissingRuntimes>,
+    pub test_merges: &'a [TestMerge],
+}
+
+/// Leaves out the runtimes, since they're indexes into a list shared between rounds.
+impl<'a> From<&'a Round> for CompactRound<'a> {
+    fn from(round: &'a Round) -> Self {
+        // Destructured so a new field on rounds can't be left out of here by accident
+        let Round {
+            round_id,
+            timestamp,
+            revision,
+            server,
+            end_timestamp,
+            shutdown_timestamp,
+            duration_secs,
+            player_count,
+            map_name,
+            game_mode,
+            end_state,
+            station_name,
+            runtimes: _,
+            missing_runtimes,
+            test_merges,
+        } = round;
+
+        Self {
+            round_id: *round_id,
+            timestamp: *timestamp,
+            revision,
+            server,
+            end_timestamp: *end_timestamp,
+            shutdown_timestamp: *shutdown_timestamp,
+            duration_secs: *duration_secs,
+            player_count: *player_count,
+            map_name: map_name.as_deref(),
+            game_mode: game_mode.as_deref(),
+            end_state: end_state.as_deref(),
+            station_name: station_name.as_deref(),
+            runtimes: None,
+            missing_runtimes,
+            test_merges,
+        }
+    }
+}
+
+pub fn compact_data<'a>(rounds: impl IntoIterator<Item = &'a Round>) -> CompactData<'a> {
+    let mut runtimes = Vec::new();
+    let mut runtime_indexes: HashMap<CompactRuntime, usize> = HashMap::new();
+
+    let rounds = rounds
+        .into_iter()
+        .map(|round| {
+            let mut compact_round = CompactRound::from(round);
+
+            compact_round.runtimes = round.runtimes.as_ref().map(|round_runtimes| {
+                round_runtimes
+                    .iter()
+                    .map(|runtime| {
+                        let index = *runtime_indexes
+                            .entry(CompactRuntime::from(runtime))
+                            .or_insert_with(|| {
+                                runtimes.push(CompactRuntime::from(runtime));
+                                runtimes.len() - 1
+                            });
+
+                        (index, runtime.count)
+                    })
+                    .collect()
+            });
+
+            compact_round
+        })
+        .collect();
+
+    CompactData { runtimes, rounds }
+}
+
+pub fn serialize_rounds(rounds: &[Round], format: DataFormat) -> Vec<u8> {
+    match format {
+        DataFormat::Full => serde_json::to_vec(rounds),
+        DataFormat::Compact => serde_json::to_vec(&compact_data(rounds)),
+    }
+    .expect("can't serialize rounds")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_data() {
+        let rounds = [
+            Round::mock(
+                1,
+                "2022-10-01 00:00:00",
+                vec![
+                    RuntimeBatch::mock("common", "/proc/common", 5),
+                    RuntimeBatch::mock("rare", "/proc/rare", 1),
+                ],
+            ),
+            Round::mock(
+                2,
+                "2022-10-01 01:00:00",
+                vec![RuntimeBatch::mock("common", "/proc/common", 3)],
+            ),
+        ];
+
+        let compact = compact_data(&rounds);
+
+        assert_eq!(
+            compact
+                .runtimes
+                .iter()
+                .map(|runtime| runtime.exception)
+                .collect::<Vec<_>>(),
+            ["common", "rare"]
+        );
+        assert_eq!(compact.rounds[0].runtimes, Some(vec![(0, 5), (1, 1)]));
+        assert_eq!(compact.rounds[1].runtimes, Some(vec![(0, 3)]));
+
+        assert!(
+            serialize_rounds(&rounds, DataFormat::Compact).len()
+                < serialize_rounds(&rounds, DataFormat::Full).len()
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::{collections::HashSet, fmt::Write};
+
+use serde::Serialize;
+
+use crate::rounds::{Round, RoundId};
+
+#[derive(Clone, Copy, Debug)]
+pub enum ExportFormat {
+    Csv,
+
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+
+    pub fn header(self) -> Option<&'static str> {
+        match self {
+            ExportFormat::Csv => Some(
+                "round_id,timestamp,server,revision,map_name,game_mode,end_state,duration_secs,player_count,fingerprint,exception,proc_path,source_file,line,count\n",
+            ),
+
+            ExportFormat::Ndjson => None,
+        }
+    }
+}
+
+/// Keep in the same order as the CSV header.
+#[derive(Serialize)]
+struct ExportRow<'a> {
+    round_id: RoundId,
+    timestamp: String,
+    server: &'a str,
+    revision: &'a str,
+    map_name: Option<&'a str>,
+    game_mode: Option<&'a str>,
+    end_state: Option<&'a str>,
+    duration_secs: Option<u64>,
+    player_count: Option<u64>,
+
+    fingerprint: &'a str,
+    exception: &'a str,
+    proc_path: &'a str,
+    source_file: &'a str,
+    line: u64,
+    count: u64,
+}
+
+fn write_csv_field(output: &mut String, value: &str) {
+    if value.contains([',', '"', '\n', '\r']) {
+        write!(output, "\"{}\"", value.replace('"', "\"\"")).unwrap();
+    } else {
+        output.push_str(value);
+    }
+}
+
+fn display_or_empty(value: Option<impl ToString>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+impl ExportRow<'_> {
+    fn write_csv(&self, output: &mut String) {
+        let fields = [
+            self.round_id.to_string(),
+            self.timestamp.clone(),
+            self.server.to_owned(),
+            self.revision.to_owned(),
+            display_or_empty(self.map_name),
+            display_or_empty(self.game_mode),
+            display_or_empty(self.end_state),
+            display_or_empty(self.duration_secs),
+            display_or_empty(self.player_count),
+            self.fingerprint.to_owned(),
+            self.exception.to_owned(),
+            self.proc_path.to_owned(),
+            self.source_file.to_owned(),
+            self.line.to_string(),
+            self.count.to_string(),
+        ];
+
+        for (index, field) in fields.iter().enumerate() {
+            if index > 0 {
+                output.push(',');
+            }
+
+            write_csv_field(output, field);
+        }
+
+        output.push('\n');
+    }
+}
+
+/// One row per runtime in the round, leaving out hidden ones.
+pub fn write_round_rows(
+    output: &mut String,
+    format: ExportFormat,
+    round: &Round,
+    hidden: &HashSet<String>,
+) {
+    // Same format as the JSON endpoints
+    let timestamp = round.timestamp.format("%Y-%m-%dT%H:%M:%S").to_string();
+
+    for runtime in round.runtimes.iter().flatten() {
+        let fingerprint = runtime.fingerprint();
+
+        if hidden.contains(&fingerprint) {
+            continue;
+        }
+
+        let row = ExportRow {
+            round_id: round.round_id,
+            timestamp: timestamp.clone(),
+            server: &round.server,
+            revision: &round.revision,
+            map_name: round.map_name.as_deref(),
+            game_mode: round.game_mode.as_deref(),
+            end_state: round.end_state.as_deref(),
+            duration_secs: round.duration_secs,
+            player_count: round.player_count,
+            fingerprint: &fingerprint,
+            exception: &runtime.exception,
+            proc_path: &runtime.proc_path,
+            source_file: &runtime.source_file,
+            line: runtime.line,
+            count: runtime.count,
+        };
+
+        match format {
+            ExportFormat::Csv => row.write_csv(output),
+
+            ExportFormat::Ndjson => {
+                output.push_str(&serde_json::to_string(&row).expect("can't serialize row"));
+                output.push('\n');
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtimes::RuntimeBatch;
+
+    #[test]
+    fn test_write_round_rows() {
+        let mut round = Round::mock(
+            1,
+            "2022-10-01 00:00:00",
+            vec![
+                RuntimeBatch::mock("list index out of bounds, \"5\"", "/proc/lists", 2),
+                RuntimeBatch::mock("ignored", "/proc/ignored", 1),
+            ],
+        );
+        round.map_name = Some("MetaStation".to_owned());
+        round.duration_secs = Some(3600);
+
+        let hidden = HashSet::from(["ignored_______/proc/ignored".to_owned()]);
+
+        let mut csv = ExportFormat::Csv.header().unwrap().to_owned();
+        write_round_rows(&mut csv, ExportFormat::Csv, &round, &hidden);
+
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            "1,2022-10-01T00:00:00,sybil,abcdef,MetaStation,,,3600,,\"list index out of bounds, \"\"5\"\"_______/proc/lists\",\"list index out of bounds, \"\"5\"\"\",/proc/lists,code.dm,1,2"
+        );
+        assert_eq!(csv.lines().count(), 2);
+
+        let mut ndjson = String::new();
+        write_round_rows(&mut ndjson, ExportFormat::Ndjson, &round, &HashSet::new());
+
+        let rows = ndjson
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["exception"], "list index out of bounds, \"5\"");
+        assert_eq!(rows[0]["map_name"], "MetaStation");
+        assert_eq!(rows[1]["game_mode"], serde_json::Value::Null);
+    }
+}
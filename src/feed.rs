@@ -0,0 +1,277 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use chrono::NaiveDateTime;
+
+use crate::{
+    analytics::{new_runtimes_per_revision, round_runtimes, RoundRuntime},
+    rounds::Round,
+    runtimes::RuntimeBatch,
+};
+
+const MAX_ENTRIES: usize = 100;
+
+pub struct FirstObserved<'a> {
+    pub fingerprint: String,
+    pub runtime: &'a RuntimeBatch,
+    pub first_round: &'a Round,
+
+    pub count: u64,
+    pub rounds: usize,
+}
+
+/// Newest first. Ones on the earliest revision are left out, since there's nothing to compare them to.
+pub fn first_observed_runtimes<'a>(
+    rounds: impl IntoIterator<Item = &'a Round>,
+) -> Vec<FirstObserved<'a>> {
+    let mut rounds: Vec<&Round> = rounds.into_iter().collect();
+
+    let new_fingerprints: HashSet<String> = new_runtimes_per_revision(rounds.iter().copied())
+        .into_iter()
+        .filter(|revision| !revision.is_baseline)
+        .flat_map(|revision| revision.new_runtimes)
+        .map(|runtime| runtime.fingerprint)
+        .collect();
+
+    rounds.sort_by_key(|round| (round.timestamp, round.round_id));
+
+    let mut first_observed: HashMap<String, FirstObserved> = HashMap::new();
+
+    for round in rounds {
+        for RoundRuntime {
+            fingerprint,
+            runtime,
+            count,
+        } in round_runtimes(round)
+        {
+            if !new_fingerprints.contains(&fingerprint) {
+                continue;
+            }
+
+            let observed = first_observed
+                .entry(fingerprint.clone())
+                .or_insert_with(|| FirstObserved {
+                    fingerprint,
+                    runtime,
+                    first_round: round,
+                    count: 0,
+                    rounds: 0,
+                });
+
+            observed.count += count;
+            observed.rounds += 1;
+        }
+    }
+
+    let mut first_observed: Vec<FirstObserved> = first_observed.into_values().collect();
+    first_observed.sort_by_key(|observed| {
+        std::cmp::Reverse((
+            observed.first_round.timestamp,
+            observed.first_round.round_id,
+        ))
+    });
+
+    first_observed
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// Timestamps are stored without a zone, and are UTC
+fn rfc3339(timestamp: NaiveDateTime) -> String {
+    timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// Same as `encodeURIComponent`, which the viewer's links use.
+fn encode_uri_component(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'_'
+            | b'.'
+            | b'!'
+            | b'~'
+            | b'*'
+            | b'\''
+            | b'('
+            | b')' => encoded.push(byte as char),
+
+            _ => write!(encoded, "%{byte:02X}").unwrap(),
+        }
+    }
+
+    encoded
+}
+
+pub struct FeedDetails<'a> {
+    pub project_name: &'a str,
+    pub github_repository: &'a str,
+
+    pub base_url: &'a str,
+    pub self_url: &'a str,
+
+    pub now: NaiveDateTime,
+}
+
+pub fn render_feed(details: &FeedDetails, first_observed: &[FirstObserved]) -> String {
+    let FeedDetails {
+        project_name,
+        github_repository,
+        base_url,
+        self_url,
+        now,
+    } = details;
+
+    let updated = first_observed
+        .first()
+        .map_or(*now, |observed| observed.first_round.timestamp);
+
+    let mut output = String::new();
+
+    writeln!(output, r#"<?xml version="1.0" encoding="utf-8"?>"#).unwrap();
+    writeln!(output, r#"<feed xmlns="http://www.w3.org/2005/Atom">"#).unwrap();
+    writeln!(
+        output,
+        "  <title>New runtimes on {}</title>",
+        escape_xml(project_name)
+    )
+    .unwrap();
+    writeln!(output, "  <id>{}</id>", escape_xml(self_url)).unwrap();
+    writeln!(
+        output,
+        r#"  <link rel="self" href="{}"/>"#,
+        escape_xml(self_url)
+    )
+    .unwrap();
+    writeln!(output, r#"  <link href="{}/"/>"#, escape_xml(base_url)).unwrap();
+    writeln!(output, "  <updated>{}</updated>", rfc3339(updated)).unwrap();
+    writeln!(output, "  <author><name>moth-fans-runtimes</name></author>").unwrap();
+
+    for observed in first_observed.iter().take(MAX_ENTRIES) {
+        let FirstObserved {
+            fingerprint,
+            runtime,
+            first_round,
+            count,
+            rounds,
+        } = observed;
+
+        let viewer_url = format!("{base_url}/runtime/{}", encode_uri_component(fingerprint));
+
+        let content = format!(
+            "In {} at {}:{}.\n\nFirst seen in round {} on {}, at revision {}.\n\nHappened {count} times over {rounds} rounds since.",
+            runtime.proc_path,
+            runtime.source_file,
+            runtime.line,
+            first_round.round_id,
+            first_round.server,
+            first_round.revision,
+        );
+
+        writeln!(output, "  <entry>").unwrap();
+        writeln!(
+            output,
+            "    <title>{}</title>",
+            escape_xml(&runtime.exception)
+        )
+        .unwrap();
+        writeln!(output, "    <id>{}</id>", escape_xml(&viewer_url)).unwrap();
+        writeln!(output, r#"    <link href="{}"/>"#, escape_xml(&viewer_url)).unwrap();
+        writeln!(
+            output,
+            r#"    <link rel="related" href="https://github.com/{}/commit/{}"/>"#,
+            escape_xml(github_repository),
+            escape_xml(&first_round.revision)
+        )
+        .unwrap();
+        writeln!(
+            output,
+            "    <updated>{}</updated>",
+            rfc3339(first_round.timestamp)
+        )
+        .unwrap();
+        writeln!(
+            output,
+            r#"    <content type="text">{}</content>"#,
+            escape_xml(&content)
+        )
+        .unwrap();
+        writeln!(output, "  </entry>").unwrap();
+    }
+
+    writeln!(output, "</feed>").unwrap();
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_runtimes_feed() {
+        let rounds = [
+            Round::mock_with_revision(
+                1,
+                "2022-10-01 00:00:00",
+                "old",
+                RuntimeBatch::mock_each(&["already here"]),
+            ),
+            Round::mock_with_revision(
+                2,
+                "2022-10-01 01:00:00",
+                "new",
+                RuntimeBatch::mock_each(&["already here", "a < b"]),
+            ),
+            Round::mock_with_revision(
+                3,
+                "2022-10-01 02:00:00",
+                "new",
+                RuntimeBatch::mock_each(&["a < b"]),
+            ),
+            Round::mock_with_revision(
+                4,
+                "2022-10-01 03:00:00",
+                "newer",
+                RuntimeBatch::mock_each(&["newest"]),
+            ),
+        ];
+
+        let first_observed = first_observed_runtimes(&rounds);
+
+        assert_eq!(
+            first_observed
+                .iter()
+                .map(|observed| observed.runtime.exception.as_str())
+                .collect::<Vec<_>>(),
+            ["newest", "a < b"]
+        );
+        assert_eq!(first_observed[1].first_round.round_id, 2);
+        assert_eq!(first_observed[1].count, 2);
+        assert_eq!(first_observed[1].rounds, 2);
+
+        insta::assert_snapshot!(render_feed(
+            &FeedDetails {
+                project_name: "tgstation",
+                github_repository: "tgstation/tgstation",
+                base_url: "https://runtimes.moth.fans",
+                self_url: "https://runtimes.moth.fans/api/new_runtimes.atom",
+                now: NaiveDateTime::parse_from_str("2022-10-02 00:00:00", "%Y-%m-%d %H:%M:%S")
+                    .unwrap(),
+            },
+            &first_observed,
+        ));
+    }
+}
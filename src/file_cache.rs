@@ -6,15 +6,88 @@ use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
 };
 
-static CACHE_PATH: &str = "cache";
+use crate::config::{ProjectConfig, DEFAULT_PROJECT_NAME};
+
+/// Every project's caches go in their own folder in here.
+pub static CACHE_PATH: &str = "cache";
+
+/// Caches kept directly in the cache folder before projects had their own, which were all tgstation's.
+const LEGACY_CACHES: [&str; 4] = ["rounds", "test_merges", "missing_runtimes", "git_tree.json"];
 
 pub enum CacheResult<T> {
     Save(T),
     DontSave(T),
 }
 
+/// Moves caches from before projects had their own folder into the default project's,
+/// so they aren't fetched all over again.
+pub async fn migrate_legacy_cache(projects: &[ProjectConfig]) {
+    let project = match projects
+        .iter()
+        .find(|project| project.name == DEFAULT_PROJECT_NAME)
+    {
+        Some(project) => project,
+        None => return,
+    };
+
+    for name in LEGACY_CACHES {
+        // Otherwise it's a project's own cache folder
+        if projects.iter().any(|project| project.name == name) {
+            continue;
+        }
+
+        let legacy_path = PathBuf::from(CACHE_PATH).join(name);
+        if fs::metadata(&legacy_path).await.is_err() {
+            continue;
+        }
+
+        let new_path = project.cache_path(name);
+        if fs::metadata(&new_path).await.is_ok() {
+            tracing::warn!(
+                "`{}` is left over from an older version, and can be deleted",
+                legacy_path.display()
+            );
+
+            continue;
+        }
+
+        if let Err(error) = fs::create_dir_all(new_path.parent().unwrap()).await {
+            tracing::warn!("couldn't create `{}`\n{error}", new_path.display());
+            continue;
+        }
+
+        match fs::rename(&legacy_path, &new_path).await {
+            Ok(()) => {
+                tracing::info!(
+                    "moved `{}` to `{}`",
+                    legacy_path.display(),
+                    new_path.display()
+                );
+            }
+
+            Err(error) => {
+                tracing::warn!("couldn't move `{}`\n{error}", legacy_path.display());
+            }
+        }
+    }
+}
+
+/// Reads what `from_cache_or` saved, without making it if it isn't there.
+pub async fn from_cache<T: DeserializeOwned>(path: &str) -> Option<T> {
+    let contents = fs::read_to_string(PathBuf::from(CACHE_PATH).join(path))
+        .await
+        .ok()?;
+
+    serde_json::from_str(&contents).ok()
+}
+
+/// `cache_name` is what hits and misses are counted under, such as `rounds`.
 // TODO: Replace this with sqlite someday
-pub async fn from_cache_or<T, F, Ft>(path: &str, callback: F) -> color_eyre::Result<T>
+pub async fn from_cache_or<T, F, Ft>(
+    cache_name: &str,
+    path: &str,
+    callback: F,
+) -> color_eyre::Result<T>
 where
     F: FnOnce() -> Ft,
     Ft: Future<Output = color_eyre::Result<CacheResult<T>>>,
@@ -27,6 +100,8 @@ where
     let cache_file_path = PathBuf::from(CACHE_PATH).join(path);
 
     if let Ok(mut file) = fs::File::open(&cache_file_path).await {
+        crate::metrics::record_cache_lookup(cache_name, true);
+
         let mut contents = String::new();
         file.read_to_string(&mut contents).await?;
 
@@ -36,6 +111,8 @@ where
 
         Ok(value)
     } else {
+        crate::metrics::record_cache_lookup(cache_name, false);
+
         let value = match callback().await? {
             CacheResult::Save(value) => value,
 
@@ -0,0 +1,62 @@
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use crate::{rounds::Round, state::ProjectState, triage::ignored_fingerprints};
+
+/// Query parameters shared by every endpoint that looks at a subset of rounds.
+#[derive(Debug, Default, Deserialize)]
+pub struct RoundFilter {
+    pub server: Option<String>,
+
+    // Compared case insensitively
+    pub map: Option<String>,
+    pub game_mode: Option<String>,
+    pub end_state: Option<String>,
+    pub station_name: Option<String>,
+}
+
+fn matches_optional(filter: &Option<String>, value: &Option<String>) -> bool {
+    match (filter, value) {
+        (None, _) => true,
+        (Some(filter), Some(value)) => filter.eq_ignore_ascii_case(value),
+        (Some(_), None) => false,
+    }
+}
+
+impl RoundFilter {
+    pub fn matches(&self, round: &Round) -> bool {
+        if let Some(server) = &self.server {
+            if &round.server != server {
+                return false;
+            }
+        }
+
+        matches_optional(&self.map, &round.map_name)
+            && matches_optional(&self.game_mode, &round.game_mode)
+            && matches_optional(&self.end_state, &round.end_state)
+            && matches_optional(&self.station_name, &round.station_name)
+    }
+}
+
+/// Query parameters for endpoints that list runtimes.
+#[derive(Debug, Default, Deserialize)]
+pub struct RuntimeFilter {
+    /// Leaves out runtimes triaged as ignored.
+    #[serde(default)]
+    pub hide_ignored: bool,
+}
+
+impl RuntimeFilter {
+    /// Fingerprints of the runtimes to leave out.
+    pub async fn hidden_fingerprints(&self, project: &ProjectState) -> HashSet<String> {
+        if !self.hide_ignored {
+            return HashSet::new();
+        }
+
+        ignored_fingerprints(&*project.triage().await)
+            .into_iter()
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+}
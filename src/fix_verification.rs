@@ -0,0 +1,374 @@
+use std::collections::{BTreeMap, HashMap};
+
+use axum::http::StatusCode;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::ProjectConfig,
+    file_cache::{from_cache, from_cache_or, CacheResult},
+    filters::RoundFilter,
+    request::request,
+    rounds::Round,
+    state::ProjectState,
+    triage::TriageStatus,
+};
+
+/// Full or abbreviated. Nothing else, since they end up in cache paths.
+pub fn is_commit_sha(commit: &str) -> bool {
+    (7..=40).contains(&commit.len()) && commit.chars().all(|char| char.is_ascii_hexdigit())
+}
+
+// Uncached comparisons are GitHub requests, so only so many at a time
+pub const MAX_COMPARISONS_PER_REQUEST: usize = 10;
+
+pub const MAX_COMPARISONS_PER_REFRESH: usize = 30;
+
+fn comparison_cache_path(project: &ProjectConfig, commit: &str, revision: &str) -> String {
+    format!("{}/commit_ancestry/{commit}_{revision}.json", project.name)
+}
+
+/// None if GitHub doesn't know one of them. Cached forever, since it can't change.
+async fn revision_includes_commit(
+    project: &ProjectConfig,
+    commit: &str,
+    revision: &str,
+) -> color_eyre::Result<Option<bool>> {
+    from_cache_or(
+        "commit_ancestry",
+        &comparison_cache_path(project, commit, revision),
+        || async {
+            #[derive(Deserialize)]
+            struct Comparison {
+                status: String,
+            }
+
+            let response = request(
+                format!(
+                    "https://api.github.com/repos/{}/compare/{commit}...{revision}?per_page=1",
+                    project.github_repository
+                ),
+                project.github_token.as_deref(),
+            )
+            .await?;
+
+            if response.status() == StatusCode::NOT_FOUND {
+                return Ok(CacheResult::Save(None));
+            }
+
+            let comparison: Comparison = response.error_for_status()?.json().await?;
+
+            // "ahead" means the revision is the commit plus more, "behind" and "diverged" mean it's missing
+            Ok(CacheResult::Save(Some(matches!(
+                comparison.status.as_str(),
+                "ahead" | "identical"
+            ))))
+        },
+    )
+    .await
+}
+
+/// None for revisions that couldn't be compared, or weren't cached once `comparisons_left` ran out.
+pub async fn revisions_including(
+    project: &ProjectConfig,
+    commit: &str,
+    revisions: impl IntoIterator<Item = String>,
+    comparisons_left: &mut usize,
+) -> HashMap<String, Option<bool>> {
+    let mut includes_fix = HashMap::new();
+
+    for revision in revisions {
+        if includes_fix.contains_key(&revision) {
+            continue;
+        }
+
+        let includes = if revision.starts_with(commit) {
+            Some(true)
+        } else if let Some(includes) =
+            from_cache(&comparison_cache_path(project, commit, &revision)).await
+        {
+            includes
+        } else if *comparisons_left > 0 {
+            *comparisons_left -= 1;
+
+            match revision_includes_commit(project, commit, &revision).await {
+                Ok(includes) => includes,
+
+                Err(error) => {
+                    tracing::warn!("couldn't compare {revision} against {commit}\n{error:#}");
+                    crate::metrics::record_fetch_error(&project.name, "commit_comparison");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        includes_fix.insert(revision, includes);
+    }
+
+    includes_fix
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FixStatus {
+    StillOccurs,
+
+    Verified,
+
+    NotDeployed,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Occurrences {
+    pub rounds: usize,
+    pub rounds_with_runtime: usize,
+    pub count: u64,
+}
+
+impl Occurrences {
+    fn add(&mut self, count: u64) {
+        self.rounds += 1;
+
+        if count > 0 {
+            self.rounds_with_runtime += 1;
+            self.count += count;
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct RevisionVerification {
+    pub revision: String,
+
+    /// None if it couldn't be compared, leaving its rounds out of both totals.
+    pub includes_fix: Option<bool>,
+    pub first_seen: NaiveDateTime,
+
+    #[serde(flatten)]
+    pub occurrences: Occurrences,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct FixVerification {
+    pub fingerprint: String,
+    pub commit: String,
+    pub status: FixStatus,
+
+    pub with_fix: Occurrences,
+    pub without_fix: Occurrences,
+
+    pub revisions: Vec<RevisionVerification>,
+}
+
+pub fn verify_fix<'a>(
+    fingerprint: &str,
+    commit: &str,
+    rounds: impl IntoIterator<Item = &'a Round>,
+    includes_fix: &HashMap<String, Option<bool>>,
+) -> FixVerification {
+    let mut revisions: BTreeMap<&str, RevisionVerification> = BTreeMap::new();
+
+    for round in rounds {
+        let runtimes = match &round.runtimes {
+            Some(runtimes) => runtimes,
+            None => continue,
+        };
+
+        let count = runtimes
+            .iter()
+            .filter(|runtime| runtime.fingerprint() == fingerprint)
+            .map(|runtime| runtime.count)
+            .sum();
+
+        let revision = revisions
+            .entry(&round.revision)
+            .or_insert_with(|| RevisionVerification {
+                revision: round.revision.clone(),
+                includes_fix: includes_fix.get(&round.revision).copied().flatten(),
+                first_seen: round.timestamp,
+                occurrences: Occurrences::default(),
+            });
+
+        revision.first_seen = revision.first_seen.min(round.timestamp);
+        revision.occurrences.add(count);
+    }
+
+    let mut with_fix = Occurrences::default();
+    let mut without_fix = Occurrences::default();
+
+    for revision in revisions.values() {
+        let total = match revision.includes_fix {
+            Some(true) => &mut with_fix,
+            Some(false) => &mut without_fix,
+            None => continue,
+        };
+
+        total.rounds += revision.occurrences.rounds;
+        total.rounds_with_runtime += revision.occurrences.rounds_with_runtime;
+        total.count += revision.occurrences.count;
+    }
+
+    let status = if with_fix.rounds_with_runtime > 0 {
+        FixStatus::StillOccurs
+    } else if with_fix.rounds > 0 {
+        FixStatus::Verified
+    } else {
+        FixStatus::NotDeployed
+    };
+
+    let mut revisions: Vec<RevisionVerification> = revisions.into_values().collect();
+    revisions.sort_by_key(|revision| revision.first_seen);
+
+    FixVerification {
+        fingerprint: fingerprint.to_owned(),
+        commit: commit.to_owned(),
+        status,
+        with_fix,
+        without_fix,
+        revisions,
+    }
+}
+
+// The rounds aren't locked while talking to GitHub, so it doesn't hold up a refresh
+pub async fn check_fix(
+    project: &ProjectState,
+    filter: &RoundFilter,
+    fingerprint: &str,
+    commit: &str,
+    mut max_comparisons: usize,
+) -> color_eyre::Result<FixVerification> {
+    let revisions: Vec<String> = project
+        .rounds()
+        .await?
+        .iter()
+        .filter(|round| filter.matches(round))
+        .map(|round| round.revision.clone())
+        .collect();
+
+    let includes_fix =
+        revisions_including(&project.config(), commit, revisions, &mut max_comparisons).await;
+
+    Ok(verify_fix(
+        fingerprint,
+        commit,
+        project
+            .rounds()
+            .await?
+            .iter()
+            .filter(|round| filter.matches(round)),
+        &includes_fix,
+    ))
+}
+
+/// Run after refreshes, so viewing triage only needs what's cached.
+pub async fn compare_triaged_fixes(project: &ProjectState) -> color_eyre::Result<()> {
+    let fixes: Vec<String> = project
+        .triage()
+        .await
+        .values()
+        .filter(|triage| triage.status == TriageStatus::Fixed)
+        .filter_map(|triage| triage.fixed_in.clone())
+        .collect();
+
+    if fixes.is_empty() {
+        return Ok(());
+    }
+
+    let revisions: Vec<String> = project
+        .rounds()
+        .await?
+        .iter()
+        .map(|round| round.revision.clone())
+        .collect();
+
+    let mut comparisons_left = MAX_COMPARISONS_PER_REFRESH;
+
+    for commit in fixes {
+        revisions_including(
+            &project.config(),
+            &commit,
+            revisions.iter().cloned(),
+            &mut comparisons_left,
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtimes::RuntimeBatch;
+
+    #[test]
+    fn test_verify_fix() {
+        let fingerprint = "Cannot read null.len_______/proc/broken";
+        let broken = |count| {
+            vec![RuntimeBatch::mock(
+                "Cannot read null.len",
+                "/proc/broken",
+                count,
+            )]
+        };
+
+        let rounds = [
+            Round::mock_with_revision(1, "2022-10-01 00:00:00", "before", broken(5)),
+            Round::mock_with_revision(2, "2022-10-01 01:00:00", "before", broken(3)),
+            Round::mock_with_revision(3, "2022-10-01 02:00:00", "after", Vec::new()),
+            Round::mock_with_revision(4, "2022-10-01 03:00:00", "unknown", broken(1)),
+        ];
+
+        let mut includes_fix = HashMap::from([
+            ("before".to_owned(), Some(false)),
+            ("after".to_owned(), Some(true)),
+            ("unknown".to_owned(), None),
+        ]);
+
+        let verification = verify_fix(fingerprint, "abcdef0", &rounds, &includes_fix);
+
+        assert_eq!(verification.status, FixStatus::Verified);
+        assert_eq!(
+            verification.with_fix,
+            Occurrences {
+                rounds: 1,
+                rounds_with_runtime: 0,
+                count: 0,
+            }
+        );
+        assert_eq!(
+            verification.without_fix,
+            Occurrences {
+                rounds: 2,
+                rounds_with_runtime: 2,
+                count: 8,
+            }
+        );
+        assert_eq!(
+            verification
+                .revisions
+                .iter()
+                .map(|revision| revision.revision.as_str())
+                .collect::<Vec<_>>(),
+            ["before", "after", "unknown"]
+        );
+
+        includes_fix.insert("unknown".to_owned(), Some(true));
+        let verification = verify_fix(fingerprint, "abcdef0", &rounds, &includes_fix);
+        assert_eq!(verification.status, FixStatus::StillOccurs);
+        assert_eq!(verification.with_fix.count, 1);
+
+        let verification = verify_fix(fingerprint, "abcdef0", &rounds[..2], &includes_fix);
+        assert_eq!(verification.status, FixStatus::NotDeployed);
+    }
+
+    #[test]
+    fn test_is_commit_sha() {
+        assert!(is_commit_sha("abcdef0"));
+        assert!(is_commit_sha("0123456789abcdef0123456789abcdef01234567"));
+        assert!(!is_commit_sha("abc"));
+        assert!(!is_commit_sha("../../etc"));
+    }
+}
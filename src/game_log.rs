@@ -0,0 +1,119 @@
+use chrono::NaiveDateTime;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static TIMESTAMP_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?m)^\[(?P<timestamp>\d{4}-\d{2}-\d{2}[ T]\d{2}:\d{2}:\d{2})").unwrap()
+});
+
+/// Matches the start of a logged line, or a continuation of a multi-line one.
+/// Keeps what players say from being mistaken for the round's own details.
+const LINE_START: &str = r"(?m)^(?:\[[^\]]+\] | - )";
+
+/// The commit actually running, only logged when it isn't `origin`, such as when there are test merges.
+static HEAD_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"{LINE_START}HEAD: (?P<revision>[0-9a-f]{{40}})\r?$"
+    ))
+    .unwrap()
+});
+
+static ORIGIN_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"{LINE_START}origin: (?P<revision>[0-9a-f]{{40}})\r?$"
+    ))
+    .unwrap()
+});
+
+static TEST_MERGE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"{LINE_START}Test merge active of PR #(?P<number>[0-9]+) commit (?P<commit>[0-9a-f]{{40}})\r?$"
+    ))
+    .unwrap()
+});
+
+/// What can be learned about a round from its game.log, for when there's no database to ask.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GameLogDetails {
+    /// When the first line was logged.
+    pub timestamp: Option<NaiveDateTime>,
+    pub revision: Option<String>,
+
+    /// PR numbers and the commits that were merged.
+    pub test_merges: Vec<(u64, String)>,
+}
+
+pub fn parse_game_log(game_log: &str) -> GameLogDetails {
+    let timestamp = TIMESTAMP_REGEX.captures(game_log).and_then(|captures| {
+        NaiveDateTime::parse_from_str(
+            &captures["timestamp"].replace('T', " "),
+            "%Y-%m-%d %H:%M:%S",
+        )
+        .ok()
+    });
+
+    let revision = HEAD_REGEX
+        .captures(game_log)
+        .or_else(|| ORIGIN_REGEX.captures(game_log))
+        .map(|captures| captures["revision"].to_owned());
+
+    let mut test_merges: Vec<(u64, String)> = Vec::new();
+
+    for captures in TEST_MERGE_REGEX.captures_iter(game_log) {
+        let number = match captures["number"].parse() {
+            Ok(number) => number,
+            Err(_) => continue,
+        };
+
+        if test_merges.iter().all(|(existing, _)| *existing != number) {
+            test_merges.push((number, captures["commit"].to_owned()));
+        }
+    }
+
+    GameLogDetails {
+        timestamp,
+        revision,
+        test_merges,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_game_log() {
+        assert_eq!(
+            parse_game_log(include_str!("./test_data/191838-game.log")),
+            GameLogDetails {
+                timestamp: Some(
+                    NaiveDateTime::parse_from_str("2022-10-08 12:01:02", "%Y-%m-%d %H:%M:%S")
+                        .unwrap()
+                ),
+                revision: Some("13579bdf13579bdf13579bdf13579bdf13579bdf".to_owned()),
+                test_merges: vec![
+                    (70123, "89abcdef0123456789abcdef0123456789abcdef".to_owned()),
+                    (70456, "fedcba9876543210fedcba9876543210fedcba98".to_owned()),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_game_log_without_test_merges() {
+        let game_log = "\
+[2022-10-08 12:01:02.345] Starting up round ID 191839.
+ - -------------------------
+[2022-10-08 12:01:02.346] Running /tg/ revision: 2022-10-08T09:41:17+00:00
+ - origin: 0123456789abcdef0123456789abcdef01234567
+";
+
+        let details = parse_game_log(game_log);
+
+        assert_eq!(
+            details.revision.as_deref(),
+            Some("0123456789abcdef0123456789abcdef01234567")
+        );
+        assert!(details.test_merges.is_empty());
+    }
+}
@@ -0,0 +1,385 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analytics::{blame_test_merges, round_runtimes},
+    request::post_json,
+    rounds::Round,
+    runtimes::{BestGuessFilenames, RuntimeBatch},
+};
+
+/// How many suspect test merges to list.
+const MAX_SUSPECTS: usize = 3;
+
+/// Long exceptions are cut off in the title, but kept in full in the body.
+const MAX_TITLE_EXCEPTION_LENGTH: usize = 100;
+
+#[derive(Serialize)]
+pub struct IssueDraft {
+    pub fingerprint: String,
+    pub title: String,
+    pub body: String,
+}
+
+struct ServerOccurrences {
+    rounds: usize,
+    count: u64,
+}
+
+/// Where one batch of the runtime came from, with no filename if it couldn't be found.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Location<'a> {
+    source_file: &'a str,
+    line: u64,
+    filename: Option<&'a PathBuf>,
+}
+
+/// None if the runtime isn't in any of the rounds.
+pub fn draft_issue<'a>(
+    github_repository: &str,
+    rounds: impl IntoIterator<Item = &'a Round>,
+    fingerprint: &str,
+) -> Option<IssueDraft> {
+    let rounds: Vec<&Round> = rounds.into_iter().collect();
+
+    let mut occurrences = rounds
+        .iter()
+        .filter_map(|round| {
+            let round_runtime = round_runtimes(round)
+                .into_iter()
+                .find(|round_runtime| round_runtime.fingerprint == fingerprint)?;
+
+            Some((*round, round_runtime.runtime, round_runtime.count))
+        })
+        .collect::<Vec<_>>();
+
+    occurrences.sort_by_key(|(round, ..)| (round.timestamp, round.round_id));
+
+    let (first_round, first_runtime, _) = *occurrences.first()?;
+    let (last_round, ..) = *occurrences.last()?;
+
+    let mut servers: BTreeMap<&str, ServerOccurrences> = BTreeMap::new();
+
+    // Links are by revision, since line numbers drift
+    let mut locations: BTreeMap<&str, BTreeSet<Location>> = BTreeMap::new();
+    let mut revisions_in_order: Vec<&str> = Vec::new();
+
+    for (round, _, count) in &occurrences {
+        let server = servers.entry(&round.server).or_insert(ServerOccurrences {
+            rounds: 0,
+            count: 0,
+        });
+
+        server.rounds += 1;
+        server.count += count;
+
+        if !revisions_in_order.contains(&round.revision.as_str()) {
+            revisions_in_order.push(&round.revision);
+        }
+
+        let revision_locations = locations.entry(&round.revision).or_default();
+
+        // Every batch, since the same runtime can come from more than one line
+        for runtime in round
+            .runtimes
+            .iter()
+            .flatten()
+            .filter(|runtime| runtime.fingerprint() == fingerprint)
+        {
+            revision_locations.extend(batch_locations(runtime));
+        }
+    }
+
+    let mut body = String::new();
+
+    writeln!(body, "## Runtime\n").unwrap();
+    writeln!(body, "```\n{}\n```\n", first_runtime.exception).unwrap();
+    writeln!(
+        body,
+        "In `{}`, at `{}:{}`.\n",
+        first_runtime.proc_path, first_runtime.source_file, first_runtime.line
+    )
+    .unwrap();
+
+    writeln!(body, "## Locations\n").unwrap();
+
+    for revision in &revisions_in_order {
+        let short_revision = &revision[..revision.len().min(7)];
+
+        for location in &locations[revision] {
+            let line = location.line;
+
+            match location.filename {
+                Some(filename) => writeln!(
+                    body,
+                    "- [`{filename}#L{line}` at {short_revision}](https://github.com/{github_repository}/blob/{revision}/{filename}#L{line})",
+                    filename = filename.display(),
+                ),
+
+                None => writeln!(
+                    body,
+                    "- `{}:{line}` at {short_revision} (couldn't find the file)",
+                    location.source_file
+                ),
+            }
+            .unwrap();
+        }
+    }
+
+    writeln!(body, "\n## Occurrences\n").unwrap();
+    writeln!(body, "| Server | Rounds | Count |").unwrap();
+    writeln!(body, "| --- | --- | --- |").unwrap();
+
+    for (server, server_occurrences) in &servers {
+        writeln!(
+            body,
+            "| {server} | {} | {} |",
+            server_occurrences.rounds, server_occurrences.count
+        )
+        .unwrap();
+    }
+
+    writeln!(
+        body,
+        "\n{} times over {} of {} rounds, between {} and {}.\n",
+        occurrences.iter().map(|(.., count)| count).sum::<u64>(),
+        occurrences.len(),
+        rounds.len(),
+        first_round.timestamp,
+        last_round.timestamp,
+    )
+    .unwrap();
+
+    writeln!(body, "## First seen\n").unwrap();
+    writeln!(
+        body,
+        "Round {} on {}, at revision [{}](https://github.com/{github_repository}/commit/{}).\n",
+        first_round.round_id,
+        first_round.server,
+        &first_round.revision[..first_round.revision.len().min(7)],
+        first_round.revision,
+    )
+    .unwrap();
+
+    writeln!(body, "## Suspect test merges\n").unwrap();
+
+    let suspects = blame_test_merges(rounds.iter().copied(), Some(fingerprint))
+        .into_iter()
+        .next()
+        .map(|runtime_suspects| runtime_suspects.suspects)
+        .unwrap_or_default();
+
+    if suspects.is_empty() {
+        writeln!(body, "None found.").unwrap();
+    }
+
+    for suspect in suspects.iter().take(MAX_SUSPECTS) {
+        writeln!(
+            body,
+            "- #{} {} by @{}: in {} of the {} rounds with this runtime, correlation {:.2}{}",
+            suspect.details.number,
+            suspect.details.title,
+            suspect.details.author,
+            suspect.rounds_merged_with_runtime,
+            suspect.rounds_with_runtime,
+            suspect.correlation,
+            if suspect.touches_runtime_file {
+                ", changes the file"
+            } else {
+                ""
+            },
+        )
+        .unwrap();
+    }
+
+    let mut title_exception: String = first_runtime
+        .exception
+        .chars()
+        .take(MAX_TITLE_EXCEPTION_LENGTH)
+        .collect();
+
+    if title_exception.len() < first_runtime.exception.len() {
+        title_exception.push('…');
+    }
+
+    Some(IssueDraft {
+        fingerprint: fingerprint.to_owned(),
+        title: format!("Runtime in {}: {title_exception}", first_runtime.proc_path),
+        body,
+    })
+}
+
+fn batch_locations(runtime: &RuntimeBatch) -> Vec<Location<'_>> {
+    let filenames = match &runtime.best_guess_filenames {
+        Some(BestGuessFilenames::Definitely(filename)) => vec![filename],
+        Some(BestGuessFilenames::Possible(possible)) if !possible.is_empty() => {
+            possible.iter().collect()
+        }
+        _ => {
+            return vec![Location {
+                source_file: &runtime.source_file,
+                line: runtime.line,
+                filename: None,
+            }]
+        }
+    };
+
+    filenames
+        .into_iter()
+        .map(|filename| Location {
+            source_file: &runtime.source_file,
+            line: runtime.line,
+            filename: Some(filename),
+        })
+        .collect()
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CreatedIssue {
+    pub number: u64,
+    pub html_url: String,
+}
+
+pub async fn create_issue(
+    github_repository: &str,
+    github_token: &str,
+    draft: &IssueDraft,
+) -> color_eyre::Result<CreatedIssue> {
+    #[derive(Serialize)]
+    struct NewIssue<'a> {
+        title: &'a str,
+        body: &'a str,
+    }
+
+    Ok(post_json(
+        format!("https://api.github.com/repos/{github_repository}/issues"),
+        Some(github_token),
+        &NewIssue {
+            title: &draft.title,
+            body: &draft.body,
+        },
+    )
+    .await?
+    .error_for_status()?
+    .json()
+    .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rounds::{TestMerge, TestMergeDetails},
+        runtimes::RuntimeBatch,
+    };
+
+    #[test]
+    fn test_draft_issue() {
+        let runtime = |count| {
+            let mut runtime = RuntimeBatch::mock("Cannot read null.len", "/proc/broken", count);
+            runtime.best_guess_filenames = Some(BestGuessFilenames::Definitely(PathBuf::from(
+                "code/broken.dm",
+            )));
+            runtime
+        };
+
+        let mut first = Round::mock_with_revision(
+            1,
+            "2022-10-01 00:00:00",
+            "1111111111111111111111111111111111111111",
+            vec![runtime(2)],
+        );
+        first.test_merges = vec![TestMerge {
+            details: TestMergeDetails {
+                number: 70000,
+                title: "Breaks things".to_owned(),
+                author: "someone".to_owned(),
+                commit: "abc".to_owned(),
+            },
+            files_changed: Some(vec![PathBuf::from("code/broken.dm")]),
+        }];
+
+        let mut second = Round::mock_with_revision(
+            2,
+            "2022-10-01 02:00:00",
+            "2222222222222222222222222222222222222222",
+            vec![runtime(3)],
+        );
+        second.server = "basil".to_owned();
+        second.test_merges = first.test_merges.clone();
+
+        let clean = Round::mock(3, "2022-10-01 04:00:00", Vec::new());
+
+        let rounds = vec![second, first, clean];
+
+        let draft = draft_issue(
+            "tgstation/tgstation",
+            &rounds,
+            "Cannot read null.len_______/proc/broken",
+        )
+        .unwrap();
+
+        assert_eq!(draft.title, "Runtime in /proc/broken: Cannot read null.len");
+
+        insta::assert_snapshot!(draft.body);
+
+        assert!(draft_issue("tgstation/tgstation", &rounds, "not a fingerprint").is_none());
+    }
+
+    #[test]
+    fn test_draft_issue_from_several_lines() {
+        let on_line = |line, count| {
+            let mut runtime = RuntimeBatch::mock("Cannot read null.len", "/proc/broken", count);
+            runtime.line = line;
+            runtime.best_guess_filenames = Some(BestGuessFilenames::Definitely(PathBuf::from(
+                "code/broken.dm",
+            )));
+            runtime
+        };
+
+        let rounds = [
+            Round::mock_with_revision(
+                1,
+                "2022-10-01 00:00:00",
+                "1111111111111111111111111111111111111111",
+                vec![on_line(10, 2), on_line(20, 3)],
+            ),
+            Round::mock_with_revision(
+                2,
+                "2022-10-01 02:00:00",
+                "2222222222222222222222222222222222222222",
+                vec![on_line(25, 4)],
+            ),
+        ];
+
+        let body = draft_issue(
+            "tgstation/tgstation",
+            &rounds,
+            "Cannot read null.len_______/proc/broken",
+        )
+        .unwrap()
+        .body;
+
+        let locations: Vec<&str> = body
+            .lines()
+            .filter(|line| line.starts_with("- [`code/broken.dm"))
+            .collect();
+
+        assert_eq!(
+            locations,
+            [
+                "- [`code/broken.dm#L10` at 1111111](https://github.com/tgstation/tgstation/blob/1111111111111111111111111111111111111111/code/broken.dm#L10)",
+                "- [`code/broken.dm#L20` at 1111111](https://github.com/tgstation/tgstation/blob/1111111111111111111111111111111111111111/code/broken.dm#L20)",
+                "- [`code/broken.dm#L25` at 2222222](https://github.com/tgstation/tgstation/blob/2222222222222222222222222222222222222222/code/broken.dm#L25)",
+            ]
+        );
+
+        assert!(body.contains("| sybil | 2 | 9 |"));
+        assert!(body.contains("9 times over 2 of 2 rounds"));
+    }
+}
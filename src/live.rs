@@ -0,0 +1,94 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::{
+    analytics::{diff_runtimes, RuntimeDiff, DEFAULT_SPIKE_RATIO},
+    rounds::{Round, RoundId},
+};
+
+// How far a slow client can fall behind before it's told to reload
+pub const LIVE_UPDATE_BUFFER: usize = 16;
+
+#[derive(Serialize)]
+pub struct LiveUpdate {
+    /// New, or changed since the last refresh.
+    pub rounds: Vec<Round>,
+
+    pub removed_round_ids: Vec<RoundId>,
+
+    #[serde(flatten)]
+    pub diff: RuntimeDiff,
+}
+
+impl LiveUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.rounds.is_empty() && self.removed_round_ids.is_empty() && self.diff.is_empty()
+    }
+}
+
+pub fn live_update(previous: &[Round], current: &[Round]) -> LiveUpdate {
+    let previous_by_id: HashMap<RoundId, &Round> = previous
+        .iter()
+        .map(|round| (round.round_id, round))
+        .collect();
+
+    let current_ids: HashSet<RoundId> = current.iter().map(|round| round.round_id).collect();
+
+    LiveUpdate {
+        rounds: current
+            .iter()
+            .filter(|round| previous_by_id.get(&round.round_id) != Some(round))
+            .cloned()
+            .collect(),
+
+        removed_round_ids: previous
+            .iter()
+            .map(|round| round.round_id)
+            .filter(|round_id| !current_ids.contains(round_id))
+            .collect(),
+
+        diff: diff_runtimes(previous, current, DEFAULT_SPIKE_RATIO),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtimes::RuntimeBatch;
+
+    #[test]
+    fn test_live_update() {
+        let unchanged = || Round::mock(1, "2022-10-01 00:00:00", Vec::new());
+
+        let mut published_before = Round::mock(2, "2022-10-01 01:00:00", Vec::new());
+        published_before.runtimes = None;
+
+        let published_after = Round::mock(
+            2,
+            "2022-10-01 01:00:00",
+            vec![RuntimeBatch::mock("new", "/proc/new", 1)],
+        );
+
+        let dropped = Round::mock(0, "2022-09-30 23:00:00", Vec::new());
+        let new = Round::mock(3, "2022-10-01 02:00:00", Vec::new());
+
+        let update = live_update(
+            &[published_before, unchanged(), dropped],
+            &[new, published_after, unchanged()],
+        );
+
+        assert_eq!(
+            update
+                .rounds
+                .iter()
+                .map(|round| round.round_id)
+                .collect::<Vec<_>>(),
+            [3, 2]
+        );
+        assert_eq!(update.removed_round_ids, [0]);
+        assert_eq!(update.diff.new_runtimes.len(), 1);
+
+        assert!(live_update(&[unchanged()], &[unchanged()]).is_empty());
+    }
+}
@@ -0,0 +1,268 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use color_eyre::eyre::{Context, ContextCompat};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{request::request, rounds::RoundId, servers::Server};
+
+/// Somewhere to read a round's log files from.
+#[async_trait]
+pub trait LogSource: Send + Sync {
+    /// Reads a file from the round's log folder, such as `runtime.condensed.txt`.
+    /// Returns None if the file doesn't exist, which usually means the logs aren't published yet.
+    async fn read_round_file(
+        &self,
+        server: &Server,
+        round_id: RoundId,
+        timestamp: &NaiveDateTime,
+        filename: &str,
+    ) -> color_eyre::Result<Option<String>>;
+
+    /// Lists the rounds with logs in the given day's folder, for when there's no database to ask.
+    async fn list_rounds(
+        &self,
+        server: &Server,
+        date: NaiveDate,
+    ) -> color_eyre::Result<Vec<RoundId>>;
+}
+
+fn round_id_from_folder_name(name: &str) -> Option<RoundId> {
+    name.strip_prefix("round-")?.parse().ok()
+}
+
+/// Fills in `{server}`, `{year}`, `{month}`, `{day}` and `{round_id}`.
+fn fill_template(
+    template: &str,
+    server: &Server,
+    round_id: RoundId,
+    timestamp: &NaiveDateTime,
+) -> String {
+    template
+        .replace("{server}", server.log_path())
+        .replace("{year}", &timestamp.year().to_string())
+        .replace("{month}", &format!("{:02}", timestamp.month()))
+        .replace("{day}", &format!("{:02}", timestamp.day()))
+        .replace("{round_id}", &round_id.to_string())
+}
+
+/// Reads logs from a web server, such as tgstation's parsed-logs.
+pub struct HttpLogSource {
+    /// URL of a round's log folder, as a template.
+    pub url: String,
+}
+
+#[async_trait]
+impl LogSource for HttpLogSource {
+    async fn read_round_file(
+        &self,
+        server: &Server,
+        round_id: RoundId,
+        timestamp: &NaiveDateTime,
+        filename: &str,
+    ) -> color_eyre::Result<Option<String>> {
+        let request_url = format!(
+            "{}/{filename}",
+            fill_template(&self.url, server, round_id, timestamp)
+        );
+
+        tracing::debug!("loading {filename} from {request_url}");
+
+        match request(&request_url, None)
+            .await
+            .and_then(reqwest::Response::error_for_status)
+        {
+            Ok(response) => Ok(Some(response.text().await?)),
+
+            Err(error) if error.status() == Some(reqwest::StatusCode::NOT_FOUND) => Ok(None),
+
+            Err(error) => Err(error).context(format!("couldn't get {filename}")),
+        }
+    }
+
+    async fn list_rounds(
+        &self,
+        server: &Server,
+        date: NaiveDate,
+    ) -> color_eyre::Result<Vec<RoundId>> {
+        static ROUND_LINK_REGEX: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r#"href="(?P<folder>round-[0-9]+)/?""#).unwrap());
+
+        // The day's folder is whatever comes before the round's folder
+        let day_template = self
+            .url
+            .strip_suffix("round-{round_id}")
+            .context("can't list rounds unless `logs_url` ends with `round-{round_id}`")?;
+
+        let request_url =
+            fill_template(day_template, server, 0, &date.and_hms_opt(0, 0, 0).unwrap());
+
+        tracing::debug!("listing rounds from {request_url}");
+
+        let listing = match request(&request_url, None)
+            .await
+            .and_then(reqwest::Response::error_for_status)
+        {
+            Ok(response) => response.text().await?,
+
+            // Nothing was played that day
+            Err(error) if error.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+                return Ok(Vec::new())
+            }
+
+            Err(error) => return Err(error).context("couldn't list rounds"),
+        };
+
+        let mut round_ids: Vec<RoundId> = ROUND_LINK_REGEX
+            .captures_iter(&listing)
+            .filter_map(|captures| round_id_from_folder_name(&captures["folder"]))
+            .collect();
+
+        round_ids.sort_unstable();
+        round_ids.dedup();
+
+        Ok(round_ids)
+    }
+}
+
+/// Reads logs straight off the disk, in the same `data/logs/YYYY/MM/DD/round-N/` layout the game writes them in.
+pub struct LocalLogSource {
+    /// The folder containing `data/logs`. Can contain `{server}`.
+    pub directory: String,
+}
+
+#[async_trait]
+impl LogSource for LocalLogSource {
+    async fn read_round_file(
+        &self,
+        server: &Server,
+        round_id: RoundId,
+        timestamp: &NaiveDateTime,
+        filename: &str,
+    ) -> color_eyre::Result<Option<String>> {
+        let path = self
+            .day_directory(server, timestamp.date())
+            .join(format!("round-{round_id}"))
+            .join(filename);
+
+        tracing::debug!("loading {filename} from {}", path.display());
+
+        match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => Ok(Some(contents)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error).context(format!("couldn't read `{}`", path.display())),
+        }
+    }
+
+    async fn list_rounds(
+        &self,
+        server: &Server,
+        date: NaiveDate,
+    ) -> color_eyre::Result<Vec<RoundId>> {
+        let path = self.day_directory(server, date);
+
+        let mut entries = match tokio::fs::read_dir(&path).await {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => {
+                return Err(error).context(format!("couldn't list `{}`", path.display()));
+            }
+        };
+
+        let mut round_ids = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(round_id) = round_id_from_folder_name(&entry.file_name().to_string_lossy())
+            {
+                round_ids.push(round_id);
+            }
+        }
+
+        round_ids.sort_unstable();
+
+        Ok(round_ids)
+    }
+}
+
+impl LocalLogSource {
+    fn day_directory(&self, server: &Server, date: NaiveDate) -> PathBuf {
+        PathBuf::from(self.directory.replace("{server}", server.log_path())).join(format!(
+            "data/logs/{}/{:02}/{:02}",
+            date.year(),
+            date.month(),
+            date.day(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_log_source() {
+        // Unique to this run, so concurrent runs don't share it
+        let directory = std::env::temp_dir().join(format!(
+            "moth-fans-runtimes-test-local-log-source-{}",
+            std::process::id()
+        ));
+        let round_directory = directory.join("sybil/data/logs/2022/10/08/round-191838");
+
+        tokio::fs::create_dir_all(&round_directory).await.unwrap();
+        tokio::fs::write(
+            round_directory.join("runtime.condensed.txt"),
+            include_str!("./test_data/191838-runtime.condensed.txt"),
+        )
+        .await
+        .unwrap();
+
+        let log_source = LocalLogSource {
+            directory: format!("{}/{{server}}", directory.display()),
+        };
+
+        let server = crate::servers::default_servers()
+            .into_iter()
+            .find(|server| server.name == "sybil")
+            .unwrap();
+
+        let timestamp =
+            NaiveDateTime::parse_from_str("2022-10-08 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+
+        let contents = log_source
+            .read_round_file(&server, 191838, &timestamp, "runtime.condensed.txt")
+            .await
+            .unwrap()
+            .expect("couldn't find runtime.condensed.txt");
+
+        assert_eq!(
+            crate::runtimes::get_runtimes_for_round(&contents)
+                .unwrap()
+                .len(),
+            21
+        );
+
+        assert!(log_source
+            .read_round_file(&server, 191839, &timestamp, "runtime.condensed.txt")
+            .await
+            .unwrap()
+            .is_none());
+
+        assert_eq!(
+            log_source
+                .list_rounds(&server, timestamp.date())
+                .await
+                .unwrap(),
+            vec![191838]
+        );
+
+        assert!(log_source
+            .list_rounds(&server, timestamp.date().succ_opt().unwrap())
+            .await
+            .unwrap()
+            .is_empty());
+
+        tokio::fs::remove_dir_all(&directory).await.unwrap();
+    }
+}
@@ -3,15 +3,33 @@ use std::{net::SocketAddr, sync::Arc};
 use axum::{Extension, Router};
 use axum_extra::routing::SpaRouter;
 use color_eyre::eyre::Context;
+use refresh::{finish_refresh, start_refresh, RefreshTarget};
 
+mod analytics;
+mod brotli;
+mod compression;
 mod config;
+mod dashboard;
+mod export;
+mod feed;
 mod file_cache;
+mod filters;
+mod fix_verification;
+mod game_log;
+mod issues;
+mod live;
+mod log_source;
+mod metrics;
+mod missing_runtimes;
+mod refresh;
 mod request;
 mod rounds;
 mod routes;
 mod runtimes;
 mod servers;
 mod state;
+mod triage;
+mod webhooks;
 
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
@@ -20,33 +38,47 @@ async fn main() -> color_eyre::Result<()> {
 
     tracing::info!("starting moth-fans-runtimes");
 
-    let config = config::Config::read_from_file().context("error reading config")?;
+    let config_path = config::config_path_from_args(std::env::args().skip(1))?;
+    let config = config::Config::read(config_path.as_deref()).context("error reading config")?;
     let address = SocketAddr::from((config.address, config.port));
 
-    let state = state::AppState::new(config);
+    file_cache::migrate_legacy_cache(&config.projects()?).await;
 
-    let (major, minor) = state.current_db_revision().await?;
-    tracing::debug!("current db revision: {major}.{minor}");
+    let state = state::AppState::new(config)?;
 
-    {
-        tracing::debug!("loading rounds");
-        state.save_new_rounds().await?;
-        let rounds = state.rounds().await?;
-        tracing::debug!("loaded {} rounds", rounds.len());
-    }
+    for project in &state.projects {
+        if project.config().db_url.is_some() {
+            project.check_db_revision().await?;
+        } else {
+            tracing::debug!(
+                "no database for {}, discovering rounds from logs",
+                project.config().name
+            );
+        }
 
-    let spa = SpaRouter::new("/static", "dist");
+        tracing::debug!("loading rounds for {}", project.config().name);
+        start_refresh(project, RefreshTarget::All).expect("nothing else is refreshing yet");
+        finish_refresh(project).await?;
+        let rounds = project.rounds().await?;
+        tracing::debug!(
+            "loaded {} rounds for {}",
+            rounds.len(),
+            project.config().name
+        );
+    }
 
     let state_arc = Arc::new(state);
 
-    if state_arc.config.mock_runtimes_data.is_none() {
-        tokio::task::spawn(track_rounds(state_arc.clone()));
+    for project in &state_arc.projects {
+        if project.config().mock_runtimes_data.is_none() {
+            tokio::task::spawn(track_rounds(state_arc.clone(), project.clone()));
+        }
     }
 
-    let app = Router::new()
-        .merge(spa)
-        .route("/data.json", axum::routing::get(routes::data))
-        .layer(Extension(state_arc));
+    #[cfg(unix)]
+    tokio::task::spawn(reload_config_on_sighup(state_arc.clone(), config_path));
+
+    let app = router(state_arc);
 
     tracing::debug!("listening on {address}");
 
@@ -58,18 +90,82 @@ async fn main() -> color_eyre::Result<()> {
     Ok(())
 }
 
-async fn track_rounds(state: Arc<state::AppState>) {
+fn router(state: Arc<state::AppState>) -> Router {
+    let spa = SpaRouter::new("/static", "dist");
+
+    // Served both for the default project, and namespaced under /projects/:project
+    let project_routes = Router::new()
+        .route("/data.json", axum::routing::get(routes::data))
+        .route(
+            "/api/admin/refresh",
+            axum::routing::get(routes::admin_refresh_status).post(routes::start_admin_refresh),
+        )
+        .route("/api/blame", axum::routing::get(routes::blame))
+        .route("/api/export.csv", axum::routing::get(routes::export_csv))
+        .route(
+            "/api/export.ndjson",
+            axum::routing::get(routes::export_ndjson),
+        )
+        .route(
+            "/api/fix_verification",
+            axum::routing::get(routes::fix_verification),
+        )
+        .route("/api/live", axum::routing::get(routes::live))
+        .route(
+            "/api/new_runtimes.atom",
+            axum::routing::get(routes::new_runtimes_feed),
+        )
+        .route(
+            "/api/issue",
+            axum::routing::get(routes::issue_draft).post(routes::create_issue_from_draft),
+        )
+        .route(
+            "/api/revisions/new_runtimes",
+            axum::routing::get(routes::new_runtimes),
+        )
+        .route("/api/runtimes", axum::routing::get(routes::aggregate))
+        .route("/api/servers", axum::routing::get(routes::servers))
+        .route("/api/status", axum::routing::get(routes::status))
+        .route(
+            "/api/triage",
+            axum::routing::get(routes::triage)
+                .put(routes::set_triage)
+                .delete(routes::clear_triage),
+        )
+        .route("/api/trends", axum::routing::get(routes::trends));
+
+    Router::new()
+        .merge(spa)
+        .merge(project_routes.clone())
+        .nest("/projects/:project", project_routes)
+        .route("/api/projects", axum::routing::get(routes::projects))
+        .route("/healthz", axum::routing::get(routes::healthz))
+        .route("/metrics", axum::routing::get(routes::metrics))
+        .route("/readyz", axum::routing::get(routes::readyz))
+        .layer(Extension(state))
+}
+
+async fn track_rounds(state: Arc<state::AppState>, project: Arc<state::ProjectState>) {
     loop {
-        tokio::time::sleep(std::time::Duration::from_secs(
-            state.config.rounds_cache_delay_secs,
-        ))
-        .await;
+        // Read every time, since it can change when the config is reloaded
+        let rounds_cache_delay_secs = state.config().rounds_cache_delay_secs;
+
+        tokio::time::sleep(std::time::Duration::from_secs(rounds_cache_delay_secs)).await;
 
-        tracing::trace!("updating rounds cache");
+        tracing::trace!("updating rounds cache for {}", project.config().name);
+
+        if start_refresh(&project, RefreshTarget::All).is_err() {
+            tracing::debug!("a refresh is already running, skipping this one");
+            continue;
+        }
 
-        match state.save_new_rounds().await {
+        match finish_refresh(&project).await {
             Ok(()) => {
                 tracing::trace!("updated rounds cache");
+
+                if let Err(error) = fix_verification::compare_triaged_fixes(&project).await {
+                    tracing::error!("error comparing fixes: {error}");
+                }
             }
 
             Err(error) => {
@@ -78,3 +174,28 @@ async fn track_rounds(state: Arc<state::AppState>) {
         }
     }
 }
+
+#[cfg(unix)]
+async fn reload_config_on_sighup(
+    state: Arc<state::AppState>,
+    config_path: Option<std::path::PathBuf>,
+) {
+    let mut hangups = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(error) => {
+            tracing::warn!("couldn't listen for SIGHUP, config won't be reloaded\n{error}");
+            return;
+        }
+    };
+
+    while hangups.recv().await.is_some() {
+        tracing::info!("got SIGHUP, reloading config");
+
+        let result = config::Config::read(config_path.as_deref())
+            .and_then(|new_config| state.reload_config(new_config));
+
+        if let Err(error) = result {
+            tracing::error!("couldn't reload config, keeping the old one\n{error:?}");
+        }
+    }
+}
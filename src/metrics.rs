@@ -0,0 +1,358 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+
+use crate::{request::github_rate_limit, state::AppState};
+
+const REFRESH_BUCKETS: &[f64] = &[1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+const RESPONSE_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+/// How many of the most common runtimes per server get their own gauge.
+const TOP_RUNTIMES_PER_SERVER: usize = 10;
+
+/// Everything recorded as it happens. Metrics that can be read off the state, like how many
+/// rounds are loaded, are worked out when scraped instead.
+#[derive(Default)]
+struct Metrics {
+    refresh_duration: Mutex<BTreeMap<String, Histogram>>,
+    data_response_duration: Mutex<BTreeMap<String, Histogram>>,
+
+    /// By project and kind.
+    fetch_errors: Mutex<BTreeMap<(String, &'static str), u64>>,
+
+    github_requests: AtomicU64,
+
+    /// By the name `from_cache_or` was given, such as `rounds`.
+    cache_hits: Mutex<BTreeMap<String, u64>>,
+    cache_misses: Mutex<BTreeMap<String, u64>>,
+}
+
+static METRICS: Lazy<Metrics> = Lazy::new(Metrics::default);
+
+struct Histogram {
+    buckets: &'static [f64],
+
+    /// Not cumulative, the last one is for everything past the biggest bucket.
+    counts: Vec<u64>,
+    sum: f64,
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Self {
+        Self {
+            buckets,
+            counts: vec![0; buckets.len() + 1],
+            sum: 0.0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        let index = self
+            .buckets
+            .iter()
+            .position(|bucket| value <= *bucket)
+            .unwrap_or(self.buckets.len());
+
+        self.counts[index] += 1;
+        self.sum += value;
+    }
+
+    fn render(&self, output: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+
+        for (bucket, count) in self.buckets.iter().zip(&self.counts) {
+            cumulative += count;
+            writeln!(
+                output,
+                "{name}_bucket{{{labels},le=\"{bucket}\"}} {cumulative}"
+            )
+            .unwrap();
+        }
+
+        cumulative += self.counts.last().unwrap();
+        writeln!(output, "{name}_bucket{{{labels},le=\"+Inf\"}} {cumulative}").unwrap();
+        writeln!(output, "{name}_sum{{{labels}}} {}", self.sum).unwrap();
+        writeln!(output, "{name}_count{{{labels}}} {cumulative}").unwrap();
+    }
+}
+
+fn observe(
+    histograms: &Mutex<BTreeMap<String, Histogram>>,
+    buckets: &'static [f64],
+    project: &str,
+    duration: Duration,
+) {
+    histograms
+        .lock()
+        .unwrap()
+        .entry(project.to_owned())
+        .or_insert_with(|| Histogram::new(buckets))
+        .observe(duration.as_secs_f64());
+}
+
+pub fn record_refresh(project: &str, duration: Duration) {
+    observe(
+        &METRICS.refresh_duration,
+        REFRESH_BUCKETS,
+        project,
+        duration,
+    );
+}
+
+pub fn record_data_response(project: &str, duration: Duration) {
+    observe(
+        &METRICS.data_response_duration,
+        RESPONSE_BUCKETS,
+        project,
+        duration,
+    );
+}
+
+pub fn record_fetch_error(project: &str, kind: &'static str) {
+    *METRICS
+        .fetch_errors
+        .lock()
+        .unwrap()
+        .entry((project.to_owned(), kind))
+        .or_default() += 1;
+}
+
+pub fn record_github_request() {
+    METRICS.github_requests.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_cache_lookup(cache: &str, hit: bool) {
+    let counts = if hit {
+        &METRICS.cache_hits
+    } else {
+        &METRICS.cache_misses
+    };
+
+    *counts.lock().unwrap().entry(cache.to_owned()).or_default() += 1;
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_header(output: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(output, "# HELP {name} {help}").unwrap();
+    writeln!(output, "# TYPE {name} {kind}").unwrap();
+}
+
+/// Renders every metric in Prometheus' text format.
+pub fn render(state: &AppState) -> String {
+    let mut output = String::new();
+
+    write_header(
+        &mut output,
+        "moth_fans_runtimes_refresh_duration_seconds",
+        "histogram",
+        "How long loading rounds took.",
+    );
+    for (project, histogram) in METRICS.refresh_duration.lock().unwrap().iter() {
+        histogram.render(
+            &mut output,
+            "moth_fans_runtimes_refresh_duration_seconds",
+            &format!("project=\"{}\"", escape_label(project)),
+        );
+    }
+
+    write_header(
+        &mut output,
+        "moth_fans_runtimes_data_response_duration_seconds",
+        "histogram",
+        "How long /data.json took to respond.",
+    );
+    for (project, histogram) in METRICS.data_response_duration.lock().unwrap().iter() {
+        histogram.render(
+            &mut output,
+            "moth_fans_runtimes_data_response_duration_seconds",
+            &format!("project=\"{}\"", escape_label(project)),
+        );
+    }
+
+    write_header(
+        &mut output,
+        "moth_fans_runtimes_fetch_errors_total",
+        "counter",
+        "Failures fetching logs or GitHub data, by kind.",
+    );
+    for ((project, kind), count) in METRICS.fetch_errors.lock().unwrap().iter() {
+        writeln!(
+            output,
+            "moth_fans_runtimes_fetch_errors_total{{project=\"{}\",kind=\"{}\"}} {count}",
+            escape_label(project),
+            escape_label(kind),
+        )
+        .unwrap();
+    }
+
+    write_header(
+        &mut output,
+        "moth_fans_runtimes_github_requests_total",
+        "counter",
+        "Requests made to the GitHub API.",
+    );
+    writeln!(
+        output,
+        "moth_fans_runtimes_github_requests_total {}",
+        METRICS.github_requests.load(Ordering::Relaxed)
+    )
+    .unwrap();
+
+    if let Some(rate_limit) = github_rate_limit() {
+        write_header(
+            &mut output,
+            "moth_fans_runtimes_github_rate_limit_remaining",
+            "gauge",
+            "GitHub API requests left before being rate limited, as of the last request.",
+        );
+        writeln!(
+            output,
+            "moth_fans_runtimes_github_rate_limit_remaining {}",
+            rate_limit.remaining
+        )
+        .unwrap();
+    }
+
+    for (name, counts, help) in [
+        (
+            "moth_fans_runtimes_cache_hits_total",
+            &METRICS.cache_hits,
+            "Values loaded from the file cache, by cache.",
+        ),
+        (
+            "moth_fans_runtimes_cache_misses_total",
+            &METRICS.cache_misses,
+            "Values that weren't in the file cache, by cache.",
+        ),
+    ] {
+        write_header(&mut output, name, "counter", help);
+        for (cache, count) in counts.lock().unwrap().iter() {
+            writeln!(
+                output,
+                "{name}{{cache=\"{}\"}} {count}",
+                escape_label(cache)
+            )
+            .unwrap();
+        }
+    }
+
+    // Each metric's samples have to be together, so these are written separately and joined at the end
+    let mut rounds_loaded = String::new();
+    let mut runtime_counts = String::new();
+
+    for project in &state.projects {
+        let project_label = escape_label(&project.config().name);
+
+        // Skipped while new rounds are being swapped in rather than waiting, so scrapes never block
+        let rounds = match project.try_rounds() {
+            Ok(Some(rounds)) => rounds,
+            _ => continue,
+        };
+
+        writeln!(
+            rounds_loaded,
+            "moth_fans_runtimes_rounds_loaded{{project=\"{project_label}\"}} {}",
+            rounds.len()
+        )
+        .unwrap();
+
+        let mut counts: BTreeMap<&str, HashMap<(&str, &str), u64>> = BTreeMap::new();
+
+        for round in rounds.iter() {
+            let server_counts = counts.entry(&round.server).or_default();
+
+            for runtime in round.runtimes.iter().flatten() {
+                *server_counts
+                    .entry((&runtime.exception, &runtime.proc_path))
+                    .or_default() += runtime.count;
+            }
+        }
+
+        for (server, server_counts) in counts {
+            let mut server_counts: Vec<_> = server_counts.into_iter().collect();
+            server_counts.sort_by(|(a_key, a_count), (b_key, b_count)| {
+                b_count.cmp(a_count).then(a_key.cmp(b_key))
+            });
+
+            for ((exception, proc_path), count) in
+                server_counts.into_iter().take(TOP_RUNTIMES_PER_SERVER)
+            {
+                writeln!(
+                    runtime_counts,
+                    "moth_fans_runtimes_runtime_count{{project=\"{project_label}\",server=\"{}\",exception=\"{}\",proc_path=\"{}\"}} {count}",
+                    escape_label(server),
+                    escape_label(exception),
+                    escape_label(proc_path),
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    write_header(
+        &mut output,
+        "moth_fans_runtimes_rounds_loaded",
+        "gauge",
+        "Rounds currently loaded.",
+    );
+    output.push_str(&rounds_loaded);
+
+    write_header(
+        &mut output,
+        "moth_fans_runtimes_runtime_count",
+        "gauge",
+        "Times the most common runtimes happened over the loaded rounds, by server.",
+    );
+    output.push_str(&runtime_counts);
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let mut histogram = Histogram::new(&[1.0, 5.0]);
+        histogram.observe(0.5);
+        histogram.observe(3.0);
+        histogram.observe(10.0);
+
+        let mut output = String::new();
+        histogram.render(&mut output, "test", "project=\"tgstation\"");
+
+        assert_eq!(
+            output,
+            "\
+test_bucket{project=\"tgstation\",le=\"1\"} 1
+test_bucket{project=\"tgstation\",le=\"5\"} 2
+test_bucket{project=\"tgstation\",le=\"+Inf\"} 3
+test_sum{project=\"tgstation\"} 13.5
+test_count{project=\"tgstation\"} 3
+"
+        );
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(
+            escape_label("bad \"index\"\\\nline"),
+            "bad \\\"index\\\"\\\\\\nline"
+        );
+    }
+}
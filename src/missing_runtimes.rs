@@ -0,0 +1,206 @@
+use std::path::PathBuf;
+
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
+
+use crate::{config::ProjectConfig, rounds::RoundId};
+
+/// How long to wait before retrying a round whose runtimes couldn't be loaded.
+/// Doubles with every failed attempt, up to `MAX_ATTEMPTS`.
+const BASE_RETRY_DELAY_MINUTES: i64 = 10;
+
+/// After the logs 404 this many times, they're considered gone for good.
+/// Other failures keep being retried, since they're usually outages.
+const MAX_ATTEMPTS: u32 = 8;
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", content = "details", rename_all = "snake_case")]
+pub enum MissingRuntimesReason {
+    /// The round was played on a server we don't know where to find the logs for.
+    UnknownServer(u16),
+
+    /// The logs 404'd, either because they haven't been published yet or because they never will be,
+    /// such as when the round crashed.
+    NotPublished,
+
+    FetchFailed(String),
+    ParseFailed(String),
+}
+
+impl MissingRuntimesReason {
+    /// Matches the serialized `kind`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::UnknownServer(_) => "unknown_server",
+            Self::NotPublished => "not_published",
+            Self::FetchFailed(_) => "fetch_failed",
+            Self::ParseFailed(_) => "parse_failed",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct MissingRuntimes {
+    pub reason: MissingRuntimesReason,
+    pub attempts: u32,
+    pub last_attempt: NaiveDateTime,
+
+    /// None when the runtimes are permanently missing.
+    pub next_attempt: Option<NaiveDateTime>,
+
+    /// Only once the logs are confirmed gone, after which the round is cached without runtimes.
+    pub permanent: bool,
+}
+
+impl MissingRuntimes {
+    pub fn cache_file_path(project: &ProjectConfig, round_id: RoundId) -> PathBuf {
+        project.cache_path(&format!("missing_runtimes/{round_id}.json"))
+    }
+
+    pub fn after_failure(
+        previous: Option<&MissingRuntimes>,
+        reason: MissingRuntimesReason,
+        now: NaiveDateTime,
+    ) -> Self {
+        let attempts = previous.map_or(0, |previous| previous.attempts) + 1;
+        let permanent =
+            matches!(reason, MissingRuntimesReason::NotPublished) && attempts >= MAX_ATTEMPTS;
+
+        let next_attempt = match reason {
+            _ if permanent => None,
+
+            // The server could be added to the config at any time, and checking is free
+            MissingRuntimesReason::UnknownServer(_) => Some(now),
+
+            _ => Some(
+                now + Duration::minutes(
+                    BASE_RETRY_DELAY_MINUTES << (attempts.min(MAX_ATTEMPTS) - 1),
+                ),
+            ),
+        };
+
+        Self {
+            reason,
+            attempts,
+            last_attempt: now,
+            next_attempt,
+            permanent,
+        }
+    }
+
+    pub fn should_retry(&self, now: NaiveDateTime) -> bool {
+        match self.next_attempt {
+            Some(next_attempt) => now >= next_attempt,
+            None => !self.permanent,
+        }
+    }
+
+    pub async fn load(project: &ProjectConfig, round_id: RoundId) -> Option<Self> {
+        let path = Self::cache_file_path(project, round_id);
+
+        let mut file = tokio::fs::File::open(&path).await.ok()?;
+        let mut contents = String::new();
+
+        if let Err(error) = file.read_to_string(&mut contents).await {
+            tracing::warn!("couldn't read `{}`\n{error}", path.display());
+            return None;
+        }
+
+        match serde_json::from_str(&contents) {
+            Ok(missing_runtimes) => Some(missing_runtimes),
+            Err(error) => {
+                tracing::warn!("couldn't deserialize `{}`\n{error}", path.display());
+                None
+            }
+        }
+    }
+
+    pub async fn save(&self, project: &ProjectConfig, round_id: RoundId) {
+        let path = Self::cache_file_path(project, round_id);
+
+        if let Err(error) = tokio::fs::create_dir_all(path.parent().unwrap()).await {
+            tracing::warn!("couldn't create missing runtimes cache dir\n{error}");
+            return;
+        }
+
+        if let Err(error) = tokio::fs::write(&path, serde_json::to_string(self).unwrap()).await {
+            tracing::warn!("couldn't write `{}`\n{error}", path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2022-10-18 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        let first =
+            MissingRuntimes::after_failure(None, MissingRuntimesReason::NotPublished, now());
+        assert_eq!(first.attempts, 1);
+        assert_eq!(first.next_attempt, Some(now() + Duration::minutes(10)));
+        assert!(!first.should_retry(now() + Duration::minutes(5)));
+        assert!(first.should_retry(now() + Duration::minutes(10)));
+
+        let second = MissingRuntimes::after_failure(
+            Some(&first),
+            MissingRuntimesReason::NotPublished,
+            now(),
+        );
+        assert_eq!(second.attempts, 2);
+        assert_eq!(second.next_attempt, Some(now() + Duration::minutes(20)));
+    }
+
+    #[test]
+    fn test_gives_up_after_max_attempts() {
+        let mut missing_runtimes =
+            MissingRuntimes::after_failure(None, MissingRuntimesReason::NotPublished, now());
+
+        for _ in 1..MAX_ATTEMPTS {
+            assert!(!missing_runtimes.permanent);
+            missing_runtimes = MissingRuntimes::after_failure(
+                Some(&missing_runtimes),
+                MissingRuntimesReason::NotPublished,
+                now(),
+            );
+        }
+
+        assert!(missing_runtimes.permanent);
+        assert_eq!(missing_runtimes.next_attempt, None);
+        assert!(!missing_runtimes.should_retry(now() + Duration::days(365)));
+    }
+
+    #[test]
+    fn test_outages_are_retried() {
+        let mut missing_runtimes = None;
+
+        for _ in 0..MAX_ATTEMPTS * 2 {
+            missing_runtimes = Some(MissingRuntimes::after_failure(
+                missing_runtimes.as_ref(),
+                MissingRuntimesReason::FetchFailed("timed out".to_owned()),
+                now(),
+            ));
+        }
+
+        let missing_runtimes = missing_runtimes.unwrap();
+        assert!(!missing_runtimes.permanent);
+        assert_eq!(
+            missing_runtimes.next_attempt,
+            Some(now() + Duration::minutes(BASE_RETRY_DELAY_MINUTES << (MAX_ATTEMPTS - 1)))
+        );
+    }
+
+    #[test]
+    fn test_unknown_server_is_retried_immediately() {
+        let missing_runtimes =
+            MissingRuntimes::after_failure(None, MissingRuntimesReason::UnknownServer(1), now());
+
+        assert!(!missing_runtimes.permanent);
+        assert!(missing_runtimes.should_retry(now()));
+    }
+}
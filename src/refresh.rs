@@ -0,0 +1,246 @@
+use std::{io::ErrorKind, path::Path, sync::PoisonError};
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use crate::{rounds::RoundId, state::ProjectState};
+
+/// What to re-fetch. Rounds are cached individually, so a targeted refresh throws away
+/// the target's cache files and then reloads everything, which only fetches what's missing.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RefreshTarget {
+    All,
+    Round { round_id: RoundId },
+    TestMerge { number: u64 },
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RefreshStatus {
+    /// Set while a refresh is going.
+    pub running: Option<RunningRefresh>,
+    pub last: Option<RefreshOutcome>,
+
+    /// When the last refresh that didn't fail finished, even if it wasn't the last one.
+    pub last_success: Option<NaiveDateTime>,
+    pub last_failure: Option<RefreshFailure>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RefreshFailure {
+    pub finished_at: NaiveDateTime,
+    pub error: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RunningRefresh {
+    pub target: RefreshTarget,
+    pub started_at: NaiveDateTime,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RefreshOutcome {
+    pub target: RefreshTarget,
+    pub started_at: NaiveDateTime,
+    pub finished_at: NaiveDateTime,
+
+    #[serde(flatten)]
+    pub result: RefreshResult,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum RefreshResult {
+    Success {
+        rounds: usize,
+        rounds_missing_runtimes: usize,
+    },
+
+    Failure {
+        error: String,
+    },
+}
+
+/// Marks a refresh as started, unless one already is. Returns the one that's running if it couldn't.
+pub fn start_refresh(project: &ProjectState, target: RefreshTarget) -> Result<(), RunningRefresh> {
+    let mut status = project.refresh_status.lock().unwrap();
+
+    if let Some(running) = &status.running {
+        return Err(running.clone());
+    }
+
+    status.running = Some(RunningRefresh {
+        target,
+        started_at: chrono::Utc::now().naive_utc(),
+    });
+
+    Ok(())
+}
+
+/// Clears `running` if a refresh panics or is cancelled, which would otherwise block every refresh after it.
+struct RunningRefreshGuard<'a> {
+    project: &'a ProjectState,
+}
+
+impl Drop for RunningRefreshGuard<'_> {
+    fn drop(&mut self) {
+        let mut status = self
+            .project
+            .refresh_status
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        // Only still set if the refresh didn't finish, since nothing else can start until it's cleared
+        if status.running.take().is_some() {
+            tracing::error!(
+                "refresh for {} stopped before finishing",
+                self.project.config().name
+            );
+
+            status.last_failure = Some(RefreshFailure {
+                finished_at: chrono::Utc::now().naive_utc(),
+                error: "stopped before finishing".to_owned(),
+            });
+        }
+    }
+}
+
+/// Runs a refresh that was started with `start_refresh`, recording how it went.
+pub async fn finish_refresh(project: &ProjectState) -> color_eyre::Result<()> {
+    let RunningRefresh { target, started_at } = project
+        .refresh_status
+        .lock()
+        .unwrap()
+        .running
+        .clone()
+        .expect("finish_refresh called without start_refresh");
+
+    let _guard = RunningRefreshGuard { project };
+
+    tracing::info!("refreshing {target:?} for {}", project.config().name);
+
+    let timer = std::time::Instant::now();
+    let result = refresh(project, target).await;
+    let finished_at = chrono::Utc::now().naive_utc();
+
+    crate::metrics::record_refresh(&project.config().name, timer.elapsed());
+
+    let mut status = project.refresh_status.lock().unwrap();
+
+    status.running = None;
+    status.last = Some(RefreshOutcome {
+        target,
+        started_at,
+        finished_at,
+        result: match &result {
+            Ok((rounds, rounds_missing_runtimes)) => RefreshResult::Success {
+                rounds: *rounds,
+                rounds_missing_runtimes: *rounds_missing_runtimes,
+            },
+
+            Err(error) => RefreshResult::Failure {
+                error: format!("{error:#}"),
+            },
+        },
+    });
+
+    match &result {
+        Ok(_) => status.last_success = Some(finished_at),
+
+        Err(error) => {
+            status.last_failure = Some(RefreshFailure {
+                finished_at,
+                error: format!("{error:#}"),
+            })
+        }
+    }
+
+    result.map(|_| ())
+}
+
+/// Returns how many rounds were loaded, and how many of them are missing runtimes.
+async fn refresh(
+    project: &ProjectState,
+    target: RefreshTarget,
+) -> color_eyre::Result<(usize, usize)> {
+    let config = project.config();
+
+    match target {
+        RefreshTarget::All => {}
+
+        RefreshTarget::Round { round_id } => {
+            for path in [
+                format!("rounds/{round_id}.json"),
+                format!("discovered_rounds/{round_id}.json"),
+                format!("missing_runtimes/{round_id}.json"),
+            ] {
+                remove_cache_file(&config.cache_path(&path)).await?;
+            }
+        }
+
+        RefreshTarget::TestMerge { number } => {
+            // Rounds keep a copy of their test merges, and so do rounds discovered from logs
+            let round_ids: Vec<RoundId> = project
+                .rounds()
+                .await?
+                .iter()
+                .filter(|round| {
+                    round
+                        .test_merges
+                        .iter()
+                        .any(|test_merge| test_merge.details.number == number)
+                })
+                .map(|round| round.round_id)
+                .collect();
+
+            for round_id in round_ids {
+                for path in [
+                    format!("rounds/{round_id}.json"),
+                    format!("discovered_rounds/{round_id}.json"),
+                ] {
+                    remove_cache_file(&config.cache_path(&path)).await?;
+                }
+            }
+
+            let prefix = format!("{number}_");
+
+            if let Ok(mut entries) = tokio::fs::read_dir(config.cache_path("test_merges")).await {
+                while let Some(entry) = entries.next_entry().await? {
+                    if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                        remove_cache_file(&entry.path()).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    project.save_new_rounds().await?;
+
+    let rounds = project.rounds().await?;
+
+    if let RefreshTarget::Round { round_id } = target {
+        if !rounds.iter().any(|round| round.round_id == round_id) {
+            color_eyre::eyre::bail!("round {round_id} isn't one of the last week's rounds");
+        }
+    }
+
+    Ok((
+        rounds.len(),
+        rounds
+            .iter()
+            .filter(|round| round.missing_runtimes.is_some())
+            .count(),
+    ))
+}
+
+async fn remove_cache_file(path: &Path) -> color_eyre::Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => {
+            tracing::debug!("removed cache file {}", path.display());
+            Ok(())
+        }
+
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error.into()),
+    }
+}
@@ -1,8 +1,82 @@
-pub async fn request(url: impl reqwest::IntoUrl) -> Result<reqwest::Response, reqwest::Error> {
-    let client = reqwest::ClientBuilder::new()
+use std::sync::Mutex;
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+/// From the headers of the last GitHub API response.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct GitHubRateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset_at: NaiveDateTime,
+    pub checked_at: NaiveDateTime,
+}
+
+static GITHUB_RATE_LIMIT: Mutex<Option<GitHubRateLimit>> = Mutex::new(None);
+
+pub fn github_rate_limit() -> Option<GitHubRateLimit> {
+    *GITHUB_RATE_LIMIT.lock().unwrap()
+}
+
+fn record_github_rate_limit(response: &reqwest::Response) {
+    let header =
+        |name: &str| -> Option<u64> { response.headers().get(name)?.to_str().ok()?.parse().ok() };
+
+    if let (Some(limit), Some(remaining), Some(reset)) = (
+        header("x-ratelimit-limit"),
+        header("x-ratelimit-remaining"),
+        header("x-ratelimit-reset"),
+    ) {
+        *GITHUB_RATE_LIMIT.lock().unwrap() = Some(GitHubRateLimit {
+            limit,
+            remaining,
+            reset_at: NaiveDateTime::from_timestamp_opt(reset as i64, 0).unwrap_or_default(),
+            checked_at: chrono::Utc::now().naive_utc(),
+        });
+    }
+}
+
+fn client() -> reqwest::Client {
+    reqwest::ClientBuilder::new()
         .user_agent("moth-fans-runtimes")
         .build()
-        .expect("failed to build reqwest client");
+        .expect("failed to build reqwest client")
+}
+
+fn after_response(response: &reqwest::Response) {
+    if response.url().host_str() == Some("api.github.com") {
+        crate::metrics::record_github_request();
+        record_github_rate_limit(response);
+    }
+}
+
+pub async fn request(
+    url: impl reqwest::IntoUrl,
+    bearer_token: Option<&str>,
+) -> Result<reqwest::Response, reqwest::Error> {
+    let mut request = client().get(url);
+
+    if let Some(bearer_token) = bearer_token {
+        request = request.bearer_auth(bearer_token);
+    }
+
+    let response = request.send().await?;
+    after_response(&response);
+    Ok(response)
+}
+
+pub async fn post_json(
+    url: impl reqwest::IntoUrl,
+    bearer_token: Option<&str>,
+    body: &impl Serialize,
+) -> Result<reqwest::Response, reqwest::Error> {
+    let mut request = client().post(url).json(body);
+
+    if let Some(bearer_token) = bearer_token {
+        request = request.bearer_auth(bearer_token);
+    }
 
-    client.get(url).send().await
+    let response = request.send().await?;
+    after_response(&response);
+    Ok(response)
 }
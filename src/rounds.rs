@@ -1,23 +1,37 @@
-use std::{collections::HashMap, path::PathBuf, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 
-use chrono::{Datelike, NaiveDateTime};
-use color_eyre::eyre::Context;
+use chrono::{NaiveDate, NaiveDateTime};
+use color_eyre::eyre::ContextCompat;
 use serde::{Deserialize, Serialize};
-use sqlx::{mysql::MySqlRow, MySqlConnection, Row};
+use sqlx::{
+    mysql::{MySqlPool, MySqlRow},
+    Row,
+};
 use tokio::io::AsyncReadExt;
 
 use crate::{
+    config::ProjectConfig,
     file_cache::{from_cache_or, CacheResult},
+    game_log::parse_game_log,
+    log_source::LogSource,
+    missing_runtimes::{MissingRuntimes, MissingRuntimesReason},
     request::request,
     runtimes::{BestGuessFilenames, RuntimeBatch},
+    servers::{server_by_port, Server},
 };
 
 pub type RoundId = i32;
 
 pub async fn load_rounds_over_cloud(
-    connection: &mut MySqlConnection,
+    pool: &MySqlPool,
+    project: &ProjectConfig,
 ) -> color_eyre::Result<Vec<Round>> {
-    let mut context = RoundCollectionContext::reload().await;
+    let mut context = RoundCollectionContext::reload(project).await;
 
     let mut rounds = Vec::new();
 
@@ -28,17 +42,28 @@ pub async fn load_rounds_over_cloud(
             round.initialize_datetime,
             round.server_port,
             round.commit_hash,
-            JSON_EXTRACT(feedback.json, '$.data.*') AS test_merges
+            round.start_datetime,
+            round.end_datetime,
+            round.shutdown_datetime,
+            round.map_name,
+            round.game_mode,
+            round.end_state,
+            round.station_name,
+            JSON_EXTRACT(feedback.json, '$.data.*') AS test_merges,
+            CAST(JSON_EXTRACT(round_end_stats.json, '$.data.players.total') AS UNSIGNED) AS player_count
         FROM
             round
                 LEFT JOIN
             feedback ON feedback.round_id = round.id
                 AND feedback.key_name = 'testmerged_prs'
+                LEFT JOIN
+            feedback AS round_end_stats ON round_end_stats.round_id = round.id
+                AND round_end_stats.key_name = 'round_end_stats'
         WHERE round.initialize_datetime >= NOW() - INTERVAL 7 DAY
         ORDER BY round.id DESC
     "#,
     )
-    .fetch_all(connection)
+    .fetch_all(pool)
     .await?
     .into_iter()
     {
@@ -65,15 +90,177 @@ async fn load_round_from_row(
     context: &mut RoundCollectionContext,
     row: MySqlRow,
 ) -> color_eyre::Result<Round> {
-    let round_id = row.try_get("id")?;
+    let record = RoundRecord {
+        round_id: row.try_get("id")?,
+        port: row.try_get("server_port")?,
+        timestamp: row.get("initialize_datetime"),
+        revision: row.try_get("commit_hash")?,
+        test_merges: match row.get::<Option<String>, _>("test_merges") {
+            Some(test_merge_details) => serde_json::from_str(&test_merge_details)?,
+            None => Vec::new(),
+        },
+    };
+
+    let mut round = load_round(context, record).await?;
+
+    update_details_from_row(&mut round, &row)?;
+
+    Ok(round)
+}
+
+/// Finds rounds from the last week by listing the log folders, for projects without a database.
+/// Details only the database knows, such as the map and player count, are left empty.
+pub async fn load_rounds_from_logs(project: &ProjectConfig) -> color_eyre::Result<Vec<Round>> {
+    let mut context = RoundCollectionContext::reload(project).await;
+
+    let today = chrono::Utc::now().date_naive();
 
-    from_cache_or(&format!("rounds/{round_id}.json"), || async {
-        let mut test_merge_details: Vec<TestMergeDetails> =
-            match row.get::<Option<String>, _>("test_merges") {
-                Some(test_merge_details) => serde_json::from_str(&test_merge_details)?,
-                None => Vec::new(),
+    let mut rounds = Vec::new();
+
+    // Servers can share a log folder, and round IDs are unique across them
+    let mut seen_round_ids = HashSet::new();
+
+    for server in &project.servers {
+        for days_ago in 0..7 {
+            let date = today - chrono::Duration::days(days_ago);
+
+            let round_ids = match context.log_source.list_rounds(server, date).await {
+                Ok(round_ids) => round_ids,
+                Err(error) => {
+                    tracing::warn!(
+                        "couldn't list rounds for {} on {date}\n{error}",
+                        server.name
+                    );
+                    crate::metrics::record_fetch_error(&project.name, "round_listing");
+
+                    continue;
+                }
             };
 
+            for round_id in round_ids {
+                if !seen_round_ids.insert(round_id) {
+                    continue;
+                }
+
+                let record = match discover_round(&mut context, server, round_id, date).await {
+                    Ok(Some(record)) => record,
+
+                    Ok(None) => {
+                        tracing::debug!("round {round_id} has no game log yet, skipping");
+                        continue;
+                    }
+
+                    Err(error) => {
+                        tracing::warn!("failed to discover round {round_id}\n{error}");
+                        continue;
+                    }
+                };
+
+                match load_round(&mut context, record).await {
+                    Ok(round) => rounds.push(round),
+                    Err(error) => {
+                        tracing::warn!("failed to load round {round_id}\n{error}");
+                    }
+                }
+            }
+        }
+    }
+
+    rounds.sort_by_key(|round| std::cmp::Reverse(round.round_id));
+
+    Ok(rounds)
+}
+
+/// Reads what the database would have told us about a round from its game log.
+/// Returns None if the game log isn't published yet.
+async fn discover_round(
+    context: &mut RoundCollectionContext,
+    server: &Server,
+    round_id: RoundId,
+    date: NaiveDate,
+) -> color_eyre::Result<Option<RoundRecord>> {
+    let cache_path = format!("{}/discovered_rounds/{round_id}.json", context.project.name);
+
+    from_cache_or("discovered_rounds", &cache_path, || async {
+        // The folder's date is all we know until the game log is read
+        let folder_timestamp = date.and_hms_opt(0, 0, 0).unwrap();
+
+        let mut game_log = None;
+
+        // Older rounds use game.txt
+        for filename in ["game.log", "game.txt"] {
+            game_log = context
+                .log_source
+                .read_round_file(server, round_id, &folder_timestamp, filename)
+                .await?;
+
+            if game_log.is_some() {
+                break;
+            }
+        }
+
+        let details = match game_log {
+            Some(game_log) => parse_game_log(&game_log),
+            None => return Ok(CacheResult::DontSave(None)),
+        };
+
+        let mut should_save = true;
+
+        let mut test_merges = Vec::new();
+        for (number, commit) in details.test_merges {
+            match context.test_merge_details_from_log(number, commit).await {
+                CacheResult::Save(details) => test_merges.push(details),
+                CacheResult::DontSave(details) => {
+                    should_save = false;
+                    test_merges.push(details);
+                }
+            }
+        }
+
+        let record = Some(RoundRecord {
+            round_id,
+            port: server.port,
+            timestamp: details.timestamp.unwrap_or(folder_timestamp),
+            revision: details
+                .revision
+                .context("couldn't find the revision in the game log")?,
+            test_merges,
+        });
+
+        if should_save {
+            Ok(CacheResult::Save(record))
+        } else {
+            Ok(CacheResult::DontSave(record))
+        }
+    })
+    .await
+}
+
+/// Everything needed to find a round's logs and test merges, from either the database or the logs themselves.
+#[derive(Deserialize, Serialize)]
+struct RoundRecord {
+    round_id: RoundId,
+    port: u16,
+    timestamp: NaiveDateTime,
+    revision: String,
+    test_merges: Vec<TestMergeDetails>,
+}
+
+async fn load_round(
+    context: &mut RoundCollectionContext,
+    record: RoundRecord,
+) -> color_eyre::Result<Round> {
+    let RoundRecord {
+        round_id,
+        port,
+        timestamp,
+        revision,
+        test_merges: mut test_merge_details,
+    } = record;
+
+    let cache_path = format!("{}/rounds/{round_id}.json", context.project.name);
+
+    from_cache_or("rounds", &cache_path, || async {
         // https://github.com/tgstation/tgstation/issues/70292
         test_merge_details.dedup_by_key(|test_merge| test_merge.number);
 
@@ -82,33 +269,67 @@ async fn load_round_from_row(
             test_merges.push(context.test_merge_from_details(details).await);
         }
 
-        let port = row.try_get("server_port")?;
+        let now = chrono::Utc::now().naive_utc();
+        let previous_missing_runtimes = MissingRuntimes::load(&context.project, round_id).await;
+
+        let (runtimes, missing_runtimes) = match previous_missing_runtimes {
+            Some(missing_runtimes) if !missing_runtimes.should_retry(now) => {
+                tracing::trace!("not retrying runtimes for round {round_id} yet");
 
-        let timestamp = row.get("initialize_datetime");
+                (None, Some(missing_runtimes))
+            }
 
-        let runtimes = match load_runtimes_from(context, round_id, port, &timestamp).await {
-            Ok(runtimes) => Some(runtimes),
+            _ => match load_runtimes_from(context, round_id, port, &timestamp).await {
+                Ok(runtimes) => (Some(runtimes), None),
 
-            Err(error) => {
-                tracing::warn!("error loading runtimes for round {round_id}: {error}");
+                Err(reason) => {
+                    tracing::warn!("error loading runtimes for round {round_id}: {reason:?}");
+                    crate::metrics::record_fetch_error(&context.project.name, reason.kind());
 
-                None
-            }
+                    let missing_runtimes = MissingRuntimes::after_failure(
+                        previous_missing_runtimes.as_ref(),
+                        reason,
+                        now,
+                    );
+
+                    missing_runtimes.save(&context.project, round_id).await;
+
+                    (None, Some(missing_runtimes))
+                }
+            },
         };
 
-        let should_save = runtimes.is_some();
+        // Only cache rounds that won't change, since cached rounds are never loaded again.
+        // Unknown servers and outages are retried until their runtimes load.
+        let should_save = !matches!(
+            missing_runtimes,
+            Some(MissingRuntimes {
+                permanent: false,
+                ..
+            })
+        ) && context.test_merges_complete(&test_merges);
 
         let round = Round {
             round_id,
-            server: match crate::servers::server_by_port(port) {
-                Some(server) => server.name.to_owned(),
+            server: match server_by_port(&context.project.servers, port) {
+                Some(server) => server.name.clone(),
                 None => format!("unknown server: {port}"),
             },
-            revision: row.try_get("commit_hash")?,
+            revision,
 
             runtimes,
+            missing_runtimes,
 
             timestamp,
+            end_timestamp: None,
+            shutdown_timestamp: None,
+            duration_secs: None,
+            player_count: None,
+
+            map_name: None,
+            game_mode: None,
+            end_state: None,
+            station_name: None,
 
             test_merges,
         };
@@ -122,36 +343,61 @@ async fn load_round_from_row(
     .await
 }
 
+/// Fills in details that can change after the round was cached, such as when it ends.
+fn update_details_from_row(round: &mut Round, row: &MySqlRow) -> color_eyre::Result<()> {
+    let start_timestamp: Option<NaiveDateTime> = row.try_get("start_datetime")?;
+    let shutdown_timestamp: Option<NaiveDateTime> = row.try_get("shutdown_datetime")?;
+    let end_timestamp: Option<NaiveDateTime> = row
+        .try_get::<Option<NaiveDateTime>, _>("end_datetime")?
+        .or(shutdown_timestamp);
+
+    round.end_timestamp = end_timestamp;
+    round.shutdown_timestamp = shutdown_timestamp;
+
+    // Lobby time doesn't count, since there's nobody around to cause runtimes
+    round.duration_secs = end_timestamp.and_then(|end_timestamp| {
+        (end_timestamp - start_timestamp.unwrap_or(round.timestamp))
+            .num_seconds()
+            .try_into()
+            .ok()
+    });
+
+    round.player_count = row.try_get("player_count")?;
+
+    round.map_name = row.try_get("map_name")?;
+    round.game_mode = row.try_get("game_mode")?;
+    round.end_state = row.try_get("end_state")?;
+    round.station_name = row.try_get("station_name")?;
+
+    Ok(())
+}
+
 #[tracing::instrument]
 async fn load_runtimes_from(
     context: &mut RoundCollectionContext,
     round_id: RoundId,
     port: u16,
     timestamp: &NaiveDateTime,
-) -> color_eyre::Result<Vec<RuntimeBatch>> {
-    let request_url = format!(
-        "https://tgstation13.org/parsed-logs/{}/data/logs/{}/{:02}/{:02}/round-{round_id}/runtime.condensed.txt",
-        match crate::servers::server_by_port(port) {
-            Some(server) => server.name,
-            None => {
-                color_eyre::eyre::bail!("unknown server: {port}");
-            }
-        },
-        timestamp.year(),
-        timestamp.month(),
-        timestamp.day(),
-    );
-
-    tracing::debug!("loading runtimes from {request_url}");
+) -> Result<Vec<RuntimeBatch>, MissingRuntimesReason> {
+    let server = match server_by_port(&context.project.servers, port) {
+        Some(server) => server,
+        None => {
+            return Err(MissingRuntimesReason::UnknownServer(port));
+        }
+    };
 
-    let runtime_condensed_txt = request(&request_url)
+    let runtime_condensed_txt = match context
+        .log_source
+        .read_round_file(server, round_id, timestamp, "runtime.condensed.txt")
         .await
-        .and_then(reqwest::Response::error_for_status)
-        .context("couldn't get runtime.condensed.txt")?
-        .text()
-        .await?;
+    {
+        Ok(Some(runtime_condensed_txt)) => runtime_condensed_txt,
+        Ok(None) => return Err(MissingRuntimesReason::NotPublished),
+        Err(error) => return Err(MissingRuntimesReason::FetchFailed(format!("{error:#}"))),
+    };
 
-    let mut runtimes = crate::runtimes::get_runtimes_for_round(&runtime_condensed_txt)?;
+    let mut runtimes = crate::runtimes::get_runtimes_for_round(&runtime_condensed_txt)
+        .map_err(|error| MissingRuntimesReason::ParseFailed(format!("{error:#}")))?;
 
     for runtime in runtimes.iter_mut() {
         if matches!(
@@ -170,9 +416,15 @@ async fn load_runtimes_from(
 }
 
 struct RoundCollectionContext {
+    project: ProjectConfig,
+    log_source: Arc<dyn LogSource>,
+
     git_tree: HashMap<String, Vec<PathBuf>>,
 
     test_merges: HashMap<String, TestMerge>,
+
+    /// Test merges GitHub couldn't tell us about this time, so nothing with them is cached.
+    incomplete_test_merges: HashSet<u64>,
 }
 
 impl std::fmt::Debug for RoundCollectionContext {
@@ -182,14 +434,16 @@ impl std::fmt::Debug for RoundCollectionContext {
 }
 
 impl RoundCollectionContext {
-    async fn reload() -> Self {
+    async fn reload(project: &ProjectConfig) -> Self {
         let mut test_merges = HashMap::new();
 
-        tokio::fs::create_dir_all("cache/test_merges")
+        let test_merges_path = project.cache_path("test_merges");
+
+        tokio::fs::create_dir_all(&test_merges_path)
             .await
             .expect("failed to create test_merges cache dir");
 
-        if let Ok(mut read_dir) = tokio::fs::read_dir("cache/test_merges").await {
+        if let Ok(mut read_dir) = tokio::fs::read_dir(&test_merges_path).await {
             // for entry in read_dir {
             while let Ok(Some(entry)) = read_dir.next_entry().await {
                 let test_merge: TestMerge = match tokio::fs::File::open(entry.path()).await {
@@ -226,6 +480,11 @@ impl RoundCollectionContext {
                     }
                 };
 
+                // Failed lookups used to be cached, so look them up again
+                if test_merge.files_changed.is_none() {
+                    continue;
+                }
+
                 let commit = test_merge.details.commit.clone();
 
                 tracing::trace!(
@@ -239,14 +498,26 @@ impl RoundCollectionContext {
         }
 
         Self {
+            project: project.clone(),
+            log_source: project.log_source(),
             test_merges,
-            git_tree: Self::get_git_tree().await,
+            incomplete_test_merges: HashSet::new(),
+            git_tree: Self::get_git_tree(project).await,
         }
     }
 
-    #[tracing::instrument]
-    async fn get_git_tree() -> HashMap<String, Vec<PathBuf>> {
-        const GIT_TREE_CACHE_FILE: &str = "cache/git_tree.json";
+    fn test_merges_complete(&self, test_merges: &[TestMerge]) -> bool {
+        test_merges.iter().all(|test_merge| {
+            test_merge.files_changed.is_some()
+                && !self
+                    .incomplete_test_merges
+                    .contains(&test_merge.details.number)
+        })
+    }
+
+    #[tracing::instrument(skip_all, fields(project = %project.name))]
+    async fn get_git_tree(project: &ProjectConfig) -> HashMap<String, Vec<PathBuf>> {
+        let git_tree_cache_file = project.cache_path("git_tree.json");
 
         #[derive(Deserialize)]
         struct GitTreeResponse {
@@ -259,7 +530,11 @@ impl RoundCollectionContext {
         }
 
         match request(
-            "https://api.github.com/repos/tgstation/tgstation/git/trees/master?recursive=1",
+            format!(
+                "https://api.github.com/repos/{}/git/trees/master?recursive=1",
+                project.github_repository
+            ),
+            project.github_token.as_deref(),
         )
         .await
         .and_then(reqwest::Response::error_for_status)
@@ -282,7 +557,7 @@ impl RoundCollectionContext {
                     }
 
                     if let Err(error) = tokio::fs::write(
-                        GIT_TREE_CACHE_FILE,
+                        &git_tree_cache_file,
                         serde_json::to_string_pretty(&names).unwrap(),
                     )
                     .await
@@ -300,12 +575,13 @@ impl RoundCollectionContext {
 
             Err(error) => {
                 tracing::warn!("failed to get git tree from request\n{error}");
+                crate::metrics::record_fetch_error(&project.name, "git_tree");
             }
         }
 
         tracing::debug!("attempting to load git tree from cache");
 
-        if let Ok(mut file) = tokio::fs::File::open(GIT_TREE_CACHE_FILE).await {
+        if let Ok(mut file) = tokio::fs::File::open(&git_tree_cache_file).await {
             let mut contents = String::new();
 
             match file.read_to_string(&mut contents).await {
@@ -332,6 +608,78 @@ impl RoundCollectionContext {
         HashMap::new()
     }
 
+    /// Game logs only have the PR number and commit, so the rest comes from GitHub.
+    /// Falls back to placeholders that shouldn't be saved if GitHub doesn't answer.
+    async fn test_merge_details_from_log(
+        &mut self,
+        number: u64,
+        commit: String,
+    ) -> CacheResult<TestMergeDetails> {
+        if let Some(test_merge) = self.test_merges.get(&commit) {
+            return if self.incomplete_test_merges.contains(&number) {
+                CacheResult::DontSave(test_merge.details.clone())
+            } else {
+                CacheResult::Save(test_merge.details.clone())
+            };
+        }
+
+        #[derive(Deserialize)]
+        struct PullRequest {
+            title: String,
+            user: User,
+        }
+
+        #[derive(Deserialize)]
+        struct User {
+            login: String,
+        }
+
+        let pull_request = match request(
+            format!(
+                "https://api.github.com/repos/{}/pulls/{number}",
+                self.project.github_repository
+            ),
+            self.project.github_token.as_deref(),
+        )
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        {
+            Ok(response) => match response.json::<PullRequest>().await {
+                Ok(pull_request) => Some(pull_request),
+                Err(error) => {
+                    tracing::warn!("couldn't parse pull request {number}\n{error}");
+                    None
+                }
+            },
+
+            Err(error) => {
+                tracing::warn!("couldn't get pull request {number}\n{error}");
+                crate::metrics::record_fetch_error(&self.project.name, "pull_request");
+                None
+            }
+        };
+
+        match pull_request {
+            Some(pull_request) => CacheResult::Save(TestMergeDetails {
+                number,
+                title: pull_request.title,
+                author: pull_request.user.login,
+                commit,
+            }),
+
+            None => {
+                self.incomplete_test_merges.insert(number);
+
+                CacheResult::DontSave(TestMergeDetails {
+                    number,
+                    title: format!("#{number}"),
+                    author: "unknown".to_owned(),
+                    commit,
+                })
+            }
+        }
+    }
+
     async fn test_merge_from_details(&mut self, details: TestMergeDetails) -> TestMerge {
         let commit = details.commit.clone();
         let number = details.number;
@@ -344,10 +692,13 @@ impl RoundCollectionContext {
 
         let files_changed = match tokio::time::timeout(
             Duration::from_secs(8),
-            request(format!(
-                "https://api.github.com/repos/tgstation/tgstation/pulls/{}/files",
-                number
-            )),
+            request(
+                format!(
+                    "https://api.github.com/repos/{}/pulls/{}/files",
+                    self.project.github_repository, number
+                ),
+                self.project.github_token.as_deref(),
+            ),
         )
         .await
         .map(|response| response.and_then(reqwest::Response::error_for_status))
@@ -360,25 +711,25 @@ impl RoundCollectionContext {
                     filename: PathBuf,
                 }
 
-                let files: Vec<File> = match result.json().await {
-                    Ok(files) => files,
+                match result.json::<Vec<File>>().await {
+                    Ok(files) => Some(files.into_iter().map(|file| file.filename).collect()),
                     Err(error) => {
                         tracing::warn!("error parsing files changed: {}", error);
-                        Vec::new()
+                        None
                     }
-                };
-
-                Some(files.into_iter().map(|file| file.filename).collect())
+                }
             }
 
             Ok(Err(error)) => {
                 tracing::warn!("couldn't find files changed for {}\n{error}", number);
+                crate::metrics::record_fetch_error(&self.project.name, "test_merge_files");
 
                 None
             }
 
             Err(_) => {
                 tracing::warn!("timed out finding files changed for {}", number);
+                crate::metrics::record_fetch_error(&self.project.name, "test_merge_files");
 
                 None
             }
@@ -390,7 +741,13 @@ impl RoundCollectionContext {
         };
 
         self.test_merges.insert(commit.clone(), test_merge.clone());
-        let cache_file_path = test_merge.cache_file_path();
+
+        // Looked up again on the next refresh
+        if !self.test_merges_complete(std::slice::from_ref(&test_merge)) {
+            return test_merge;
+        }
+
+        let cache_file_path = test_merge.cache_file_path(&self.project);
 
         match tokio::fs::File::create(&cache_file_path).await {
             Ok(file) => {
@@ -411,7 +768,7 @@ impl RoundCollectionContext {
     }
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
 pub struct Round {
     // Details
     pub round_id: RoundId,
@@ -419,21 +776,68 @@ pub struct Round {
     pub revision: String,
     pub server: String,
 
+    pub end_timestamp: Option<NaiveDateTime>,
+    pub shutdown_timestamp: Option<NaiveDateTime>,
+    pub duration_secs: Option<u64>,
+    pub player_count: Option<u64>,
+
+    pub map_name: Option<String>,
+    pub game_mode: Option<String>,
+    pub end_state: Option<String>,
+    pub station_name: Option<String>,
+
     // Requires cloud data to collect
     pub runtimes: Option<Vec<RuntimeBatch>>,
+    /// Set when `runtimes` is None, explaining why.
+    pub missing_runtimes: Option<MissingRuntimes>,
     pub test_merges: Vec<TestMerge>,
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[cfg(test)]
+impl Round {
+    pub fn mock(round_id: RoundId, timestamp: &str, runtimes: Vec<RuntimeBatch>) -> Self {
+        Self {
+            round_id,
+            timestamp: NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S").unwrap(),
+            revision: "abcdef".to_owned(),
+            server: "sybil".to_owned(),
+            end_timestamp: None,
+            shutdown_timestamp: None,
+            duration_secs: None,
+            player_count: None,
+            map_name: None,
+            game_mode: None,
+            end_state: None,
+            station_name: None,
+            runtimes: Some(runtimes),
+            missing_runtimes: None,
+            test_merges: Vec::new(),
+        }
+    }
+
+    pub fn mock_with_revision(
+        round_id: RoundId,
+        timestamp: &str,
+        revision: &str,
+        runtimes: Vec<RuntimeBatch>,
+    ) -> Self {
+        Self {
+            revision: revision.to_owned(),
+            ..Self::mock(round_id, timestamp, runtimes)
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
 pub struct TestMerge {
     pub details: TestMergeDetails,
     pub files_changed: Option<Vec<PathBuf>>,
 }
 
 impl TestMerge {
-    pub fn cache_file_path(&self) -> PathBuf {
-        PathBuf::from(format!(
-            "cache/test_merges/{}_{}.json",
+    pub fn cache_file_path(&self, project: &ProjectConfig) -> PathBuf {
+        project.cache_path(&format!(
+            "test_merges/{}_{}.json",
             self.details.number, self.details.commit
         ))
     }
@@ -471,8 +875,3 @@ fn deserialize_string_to_u64<'de, D: serde::de::Deserializer<'de>>(
 
     deserializer.deserialize_any(StringOrNumber)
 }
-
-#[derive(Deserialize, Serialize)]
-pub struct Tree {
-    pub files: Vec<String>,
-}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::{FromRequest, Query, RequestParts},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde::Deserialize;
+
+use crate::{
+    refresh::{finish_refresh, start_refresh, RefreshTarget},
+    rounds::RoundId,
+    routes::CurrentProject,
+    state::AppState,
+};
+
+/// Rejects the request unless it has `Authorization: Bearer <admin_token>`.
+#[derive(Debug)]
+pub struct AdminAuth;
+
+#[async_trait]
+impl<B: Send> FromRequest<B> for AdminAuth {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request(request: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let Extension(state) = Extension::<Arc<AppState>>::from_request(request)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "missing state"))?;
+
+        let admin_token = match &state.config().admin_token {
+            Some(admin_token) => admin_token.clone(),
+            None => {
+                return Err((
+                    StatusCode::NOT_FOUND,
+                    "admin routes are disabled, set `admin_token` to enable them",
+                ))
+            }
+        };
+
+        let token = request
+            .headers()
+            .get("authorization")
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "));
+
+        match token {
+            Some(token) if tokens_match(token.as_bytes(), admin_token.as_bytes()) => Ok(Self),
+            _ => Err((StatusCode::UNAUTHORIZED, "invalid admin token")),
+        }
+    }
+}
+
+/// Looks at every byte whatever the given token is, so how long it takes doesn't leak how much of it was right.
+fn tokens_match(given: &[u8], expected: &[u8]) -> bool {
+    let mut difference = given.len() ^ expected.len();
+
+    for (index, expected_byte) in expected.iter().enumerate() {
+        let given_byte = given.get(index).copied().unwrap_or_default();
+        difference |= usize::from(given_byte ^ expected_byte);
+    }
+
+    difference == 0
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshQuery {
+    round_id: Option<RoundId>,
+
+    /// PR number of the test merge.
+    test_merge: Option<u64>,
+}
+
+/// Starts a refresh in the background. Poll `GET` on the same route for how it's going.
+#[tracing::instrument]
+pub async fn start_admin_refresh(
+    _: AdminAuth,
+    CurrentProject(project): CurrentProject,
+    Query(query): Query<RefreshQuery>,
+) -> impl IntoResponse {
+    let target = match (query.round_id, query.test_merge) {
+        (None, None) => RefreshTarget::All,
+        (Some(round_id), None) => RefreshTarget::Round { round_id },
+        (None, Some(number)) => RefreshTarget::TestMerge { number },
+        (Some(_), Some(_)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "only one of `round_id` and `test_merge` can be given",
+            )
+                .into_response();
+        }
+    };
+
+    if let Err(running) = start_refresh(&project, target) {
+        return (StatusCode::CONFLICT, Json(running)).into_response();
+    }
+
+    let status = project.refresh_status.lock().unwrap().clone();
+
+    tokio::task::spawn(async move {
+        if let Err(error) = finish_refresh(&project).await {
+            tracing::error!("admin refresh failed\n{error:?}");
+        }
+    });
+
+    (StatusCode::ACCEPTED, Json(status)).into_response()
+}
+
+#[tracing::instrument]
+pub async fn admin_refresh_status(
+    _: AdminAuth,
+    CurrentProject(project): CurrentProject,
+) -> impl IntoResponse {
+    Json(project.refresh_status.lock().unwrap().clone())
+}
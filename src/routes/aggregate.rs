@@ -0,0 +1,24 @@
+use axum::{extract::Query, response::IntoResponse, Json};
+
+use crate::{
+    analytics::aggregate_runtimes,
+    filters::{RoundFilter, RuntimeFilter},
+    routes::CurrentProject,
+};
+
+#[tracing::instrument]
+pub async fn aggregate(
+    CurrentProject(project): CurrentProject,
+    Query(filter): Query<RoundFilter>,
+    Query(runtime_filter): Query<RuntimeFilter>,
+) -> impl IntoResponse {
+    let hidden = runtime_filter.hidden_fingerprints(&project).await;
+    let rounds = project.rounds().await.expect("can't get rounds");
+
+    let mut aggregate = aggregate_runtimes(rounds.iter().filter(|round| filter.matches(round)));
+    aggregate
+        .runtimes
+        .retain(|runtime| !hidden.contains(&runtime.fingerprint));
+
+    Json(aggregate)
+}
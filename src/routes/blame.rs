@@ -0,0 +1,44 @@
+use axum::{extract::Query, response::IntoResponse, Json};
+use serde::Deserialize;
+
+use crate::{
+    analytics::blame_test_merges,
+    filters::{RoundFilter, RuntimeFilter},
+    routes::CurrentProject,
+};
+
+const DEFAULT_SUSPECTS_LIMIT: usize = 5;
+
+#[derive(Debug, Deserialize)]
+pub struct BlameQuery {
+    fingerprint: Option<String>,
+
+    /// How many suspects to return per runtime.
+    limit: Option<usize>,
+}
+
+#[tracing::instrument]
+pub async fn blame(
+    CurrentProject(project): CurrentProject,
+    Query(filter): Query<RoundFilter>,
+    Query(query): Query<BlameQuery>,
+    Query(runtime_filter): Query<RuntimeFilter>,
+) -> impl IntoResponse {
+    let hidden = runtime_filter.hidden_fingerprints(&project).await;
+    let rounds = project.rounds().await.expect("can't get rounds");
+
+    let mut results = blame_test_merges(
+        rounds.iter().filter(|round| filter.matches(round)),
+        query.fingerprint.as_deref(),
+    );
+
+    results.retain(|runtime_suspects| !hidden.contains(&runtime_suspects.fingerprint));
+
+    for runtime_suspects in &mut results {
+        runtime_suspects
+            .suspects
+            .truncate(query.limit.unwrap_or(DEFAULT_SUSPECTS_LIMIT));
+    }
+
+    Json(results)
+}
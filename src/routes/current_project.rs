@@ -0,0 +1,43 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    async_trait,
+    extract::{FromRequest, Path, RequestParts},
+    http::StatusCode,
+    Extension,
+};
+
+use crate::state::{AppState, ProjectState};
+
+/// The project named by the `:project` path parameter,
+/// or the default project for routes that aren't namespaced.
+#[derive(Debug)]
+pub struct CurrentProject(pub Arc<ProjectState>);
+
+#[async_trait]
+impl<B: Send> FromRequest<B> for CurrentProject {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request(request: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let Extension(state) = Extension::<Arc<AppState>>::from_request(request)
+            .await
+            .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()))?;
+
+        let project_name = match Path::<HashMap<String, String>>::from_request(request).await {
+            Ok(Path(mut params)) => params.remove("project"),
+            Err(_) => None,
+        };
+
+        match project_name {
+            Some(project_name) => match state.project(&project_name) {
+                Some(project) => Ok(Self(project.clone())),
+                None => Err((
+                    StatusCode::NOT_FOUND,
+                    format!("unknown project `{project_name}`"),
+                )),
+            },
+
+            None => Ok(Self(state.default_project().clone())),
+        }
+    }
+}
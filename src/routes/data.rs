@@ -1,61 +1,159 @@
-use std::sync::{Arc, Mutex};
+use std::{collections::HashSet, sync::Arc};
 
-use axum::{response::IntoResponse, Extension};
-use once_cell::sync::OnceCell;
+use axum::{
+    extract::Query,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
 
-use crate::{rounds::Round, state::AppState};
-
-pub static CACHED_RESPONSE: OnceCell<Mutex<(std::time::Instant, String)>> = OnceCell::new();
+use crate::{
+    compression::{preferred_encoding, CompressedBody},
+    dashboard::{serialize_rounds, DataFormat},
+    filters::RuntimeFilter,
+    rounds::Round,
+    routes::CurrentProject,
+    state::ProjectState,
+};
 
 const CACHE_SECONDS: u64 = 60;
 const CACHE_CONTROL: &str = "max-age=60";
 
-fn create_response(text: String) -> impl IntoResponse {
-    (
-        [
-            ("content-type", "application/json"),
-            ("cache-control", CACHE_CONTROL),
-        ],
-        text,
-    )
+#[derive(Debug, Deserialize)]
+pub struct DataQuery {
+    #[serde(default)]
+    format: DataFormat,
+}
+
+fn create_response(body: &CompressedBody, request_headers: &HeaderMap) -> Response {
+    let not_modified = request_headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|if_none_match| if_none_match.to_str().ok())
+        .is_some_and(|if_none_match| body.matches(if_none_match));
+
+    let mut response = if not_modified {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        let encoding = preferred_encoding(
+            request_headers
+                .get(header::ACCEPT_ENCODING)
+                .and_then(|accept_encoding| accept_encoding.to_str().ok()),
+        );
+
+        let mut response = (
+            [(header::CONTENT_TYPE, "application/json")],
+            body.encoded(encoding),
+        )
+            .into_response();
+
+        if let Some(content_encoding) = encoding.header_value() {
+            response.headers_mut().insert(
+                header::CONTENT_ENCODING,
+                HeaderValue::from_static(content_encoding),
+            );
+        }
+
+        response
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(CACHE_CONTROL),
+    );
+    headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+    headers.insert(
+        header::ETAG,
+        HeaderValue::from_str(&body.etag).expect("etag isn't a valid header"),
+    );
+
+    response
 }
 
-#[tracing::instrument]
-pub async fn data(Extension(state): Extension<Arc<AppState>>) -> impl IntoResponse {
+#[tracing::instrument(skip(headers))]
+pub async fn data(
+    CurrentProject(project): CurrentProject,
+    Query(query): Query<DataQuery>,
+    Query(runtime_filter): Query<RuntimeFilter>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
     let now = std::time::Instant::now();
+    let hidden = runtime_filter.hidden_fingerprints(&project).await;
 
-    if let Some(response_lock) = CACHED_RESPONSE.get() {
-        let lock = response_lock.lock().unwrap();
-        let last_updated = lock.0;
-        let response = &lock.1;
+    let body = if hidden.is_empty() {
+        data_response(&project, query.format, now).await
+    } else {
+        data_response_without(&project, query.format, &hidden).await
+    };
 
-        let lock_unavailable = matches!(state.try_rounds(), Ok(None));
+    crate::metrics::record_data_response(&project.config().name, now.elapsed());
 
-        if now.duration_since(last_updated).as_secs() < CACHE_SECONDS {
+    create_response(&body, &headers)
+}
+
+async fn compress(json: Vec<u8>) -> Arc<CompressedBody> {
+    Arc::new(
+        tokio::task::spawn_blocking(move || CompressedBody::new(json))
+            .await
+            .expect("compressing panicked"),
+    )
+}
+
+async fn data_response(
+    project: &ProjectState,
+    format: DataFormat,
+    now: std::time::Instant,
+) -> Arc<CompressedBody> {
+    if let Some((last_updated, body)) = project.cached_data_responses.lock().unwrap().get(&format) {
+        let lock_unavailable = matches!(project.try_rounds(), Ok(None));
+
+        if now.duration_since(*last_updated).as_secs() < CACHE_SECONDS {
             tracing::trace!("returning cached response");
-            return create_response(response.clone());
+            return body.clone();
         }
 
         if lock_unavailable {
             tracing::trace!("couldn't get lock");
-            return create_response(response.clone());
+            return body.clone();
         }
     }
 
-    let rounds: &Vec<Round> = &state.rounds().await.expect("can't get rounds");
-    let response = serde_json::to_string(&rounds).expect("can't serialize rounds");
+    let json = serialize_rounds(&project.rounds().await.expect("can't get rounds"), format);
 
-    if let Some(response_lock) = CACHED_RESPONSE.get() {
-        let mut lock = response_lock.lock().unwrap();
-        lock.0 = now;
-        lock.1 = response.clone();
-    } else {
-        CACHED_RESPONSE
-            .set(Mutex::new((now, response.clone())))
-            .expect("can't set cached response");
-    }
+    let body = compress(json).await;
+
+    project
+        .cached_data_responses
+        .lock()
+        .unwrap()
+        .insert(format, (now, body.clone()));
 
     tracing::trace!("returning fresh response");
 
-    create_response(response)
+    body
+}
+
+/// Not cached, since it depends on the triage as well as the rounds.
+async fn data_response_without(
+    project: &ProjectState,
+    format: DataFormat,
+    hidden: &HashSet<String>,
+) -> Arc<CompressedBody> {
+    let rounds: Vec<Round> = project
+        .rounds()
+        .await
+        .expect("can't get rounds")
+        .iter()
+        .map(|round| {
+            let mut round = round.clone();
+
+            if let Some(runtimes) = &mut round.runtimes {
+                runtimes.retain(|runtime| !hidden.contains(&runtime.fingerprint()));
+            }
+
+            round
+        })
+        .collect();
+
+    compress(serialize_rounds(&rounds, format)).await
 }
@@ -0,0 +1,112 @@
+use std::{collections::HashSet, sync::Arc};
+
+use axum::{
+    body::{boxed, Body, Bytes},
+    extract::Query,
+    response::IntoResponse,
+};
+
+use crate::{
+    export::{write_round_rows, ExportFormat},
+    filters::{RoundFilter, RuntimeFilter},
+    rounds::RoundId,
+    routes::CurrentProject,
+    state::ProjectState,
+};
+
+// The rounds lock is let go between chunks, so a slow download doesn't hold up a refresh
+const ROUNDS_PER_CHUNK: usize = 50;
+
+#[tracing::instrument]
+pub async fn export_csv(
+    CurrentProject(project): CurrentProject,
+    Query(filter): Query<RoundFilter>,
+    Query(runtime_filter): Query<RuntimeFilter>,
+) -> impl IntoResponse {
+    export(project, filter, runtime_filter, ExportFormat::Csv).await
+}
+
+#[tracing::instrument]
+pub async fn export_ndjson(
+    CurrentProject(project): CurrentProject,
+    Query(filter): Query<RoundFilter>,
+    Query(runtime_filter): Query<RuntimeFilter>,
+) -> impl IntoResponse {
+    export(project, filter, runtime_filter, ExportFormat::Ndjson).await
+}
+
+async fn export(
+    project: Arc<ProjectState>,
+    filter: RoundFilter,
+    runtime_filter: RuntimeFilter,
+    format: ExportFormat,
+) -> impl IntoResponse {
+    let hidden = runtime_filter.hidden_fingerprints(&project).await;
+
+    let round_ids: Vec<RoundId> = project
+        .rounds()
+        .await
+        .expect("can't get rounds")
+        .iter()
+        .filter(|round| filter.matches(round))
+        .map(|round| round.round_id)
+        .collect();
+
+    let (mut sender, body) = Body::channel();
+    let filename = format!("{}-runtimes.{}", project.config().name, format.extension());
+
+    tokio::task::spawn(async move {
+        if let Some(header) = format.header() {
+            if sender
+                .send_data(Bytes::from_static(header.as_bytes()))
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+
+        for chunk in round_ids.chunks(ROUNDS_PER_CHUNK) {
+            let chunk: HashSet<RoundId> = chunk.iter().copied().collect();
+            let mut output = String::new();
+
+            {
+                let rounds = match project.rounds().await {
+                    Ok(rounds) => rounds,
+                    Err(error) => {
+                        tracing::error!("couldn't get rounds for export\n{error:?}");
+                        return;
+                    }
+                };
+
+                // Dropped by a refresh since the export started
+                for round in rounds
+                    .iter()
+                    .filter(|round| chunk.contains(&round.round_id))
+                {
+                    write_round_rows(&mut output, format, round, &hidden);
+                }
+            }
+
+            if output.is_empty() {
+                continue;
+            }
+
+            if sender.send_data(Bytes::from(output)).await.is_err() {
+                tracing::debug!("export was cancelled");
+                return;
+            }
+        }
+    });
+
+    (
+        [
+            ("content-type", format.content_type().to_owned()),
+            (
+                "content-disposition",
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        boxed(body),
+    )
+}
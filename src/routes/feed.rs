@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{OriginalUri, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension,
+};
+use serde::Deserialize;
+
+use crate::{
+    feed::{first_observed_runtimes, render_feed, FeedDetails},
+    filters::{RoundFilter, RuntimeFilter},
+    routes::CurrentProject,
+    state::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct FeedQuery {
+    min_count: Option<u64>,
+}
+
+// Links come from the config, since the `Host` header can be anything
+#[tracing::instrument(skip(state))]
+pub async fn new_runtimes_feed(
+    Extension(state): Extension<Arc<AppState>>,
+    CurrentProject(project): CurrentProject,
+    OriginalUri(uri): OriginalUri,
+    Query(filter): Query<RoundFilter>,
+    Query(runtime_filter): Query<RuntimeFilter>,
+    Query(query): Query<FeedQuery>,
+) -> impl IntoResponse {
+    let config = project.config();
+
+    let public_url = match &state.config().public_url {
+        Some(public_url) => public_url.trim_end_matches('/').to_owned(),
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                "the feed is disabled, set `public_url` to enable it".to_owned(),
+            )
+                .into_response()
+        }
+    };
+
+    let dashboard_url = match &config.dashboard_url {
+        Some(dashboard_url) => dashboard_url.trim_end_matches('/').to_owned(),
+
+        // The dashboard at the root only shows the default project
+        None if Arc::ptr_eq(&project, state.default_project()) => public_url.clone(),
+
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                format!(
+                    "the feed is disabled for {}, set its `dashboard_url` to enable it",
+                    config.name
+                ),
+            )
+                .into_response()
+        }
+    };
+
+    let hidden = runtime_filter.hidden_fingerprints(&project).await;
+    let rounds = project.rounds().await.expect("can't get rounds");
+
+    let mut first_observed =
+        first_observed_runtimes(rounds.iter().filter(|round| filter.matches(round)));
+
+    first_observed.retain(|observed| {
+        observed.count >= query.min_count.unwrap_or_default()
+            && !hidden.contains(&observed.fingerprint)
+    });
+
+    (
+        [("content-type", "application/atom+xml; charset=utf-8")],
+        render_feed(
+            &FeedDetails {
+                project_name: &config.name,
+                github_repository: &config.github_repository,
+                base_url: &dashboard_url,
+                self_url: &format!("{public_url}{uri}"),
+                now: chrono::Utc::now().naive_utc(),
+            },
+            &first_observed,
+        ),
+    )
+        .into_response()
+}
@@ -0,0 +1,47 @@
+use axum::{extract::Query, http::StatusCode, response::IntoResponse, Json};
+use serde::Deserialize;
+
+use crate::{
+    filters::RoundFilter,
+    fix_verification::{check_fix, is_commit_sha, MAX_COMPARISONS_PER_REQUEST},
+    routes::{AdminAuth, CurrentProject},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct FixVerificationQuery {
+    fingerprint: String,
+
+    // Usually the merge commit of the pull request
+    commit: String,
+}
+
+/// Only admins can have uncached revisions compared, the rest get what refreshes compared.
+#[tracing::instrument]
+pub async fn fix_verification(
+    admin: Option<AdminAuth>,
+    CurrentProject(project): CurrentProject,
+    Query(filter): Query<RoundFilter>,
+    Query(query): Query<FixVerificationQuery>,
+) -> impl IntoResponse {
+    if !is_commit_sha(&query.commit) {
+        return (StatusCode::BAD_REQUEST, "`commit` isn't a commit hash").into_response();
+    }
+
+    let max_comparisons = match admin {
+        Some(AdminAuth) => MAX_COMPARISONS_PER_REQUEST,
+        None => 0,
+    };
+
+    Json(
+        check_fix(
+            &project,
+            &filter,
+            &query.fingerprint,
+            &query.commit,
+            max_comparisons,
+        )
+        .await
+        .expect("can't get rounds"),
+    )
+    .into_response()
+}
@@ -0,0 +1,100 @@
+use axum::{extract::Query, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    filters::RoundFilter,
+    issues::{create_issue, draft_issue, IssueDraft},
+    routes::{AdminAuth, CurrentProject},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct IssueQuery {
+    fingerprint: String,
+}
+
+#[derive(Serialize)]
+struct IssueDraftResponse {
+    #[serde(flatten)]
+    draft: IssueDraft,
+
+    new_issue_url: String,
+}
+
+#[tracing::instrument]
+pub async fn issue_draft(
+    CurrentProject(project): CurrentProject,
+    Query(filter): Query<RoundFilter>,
+    Query(query): Query<IssueQuery>,
+) -> impl IntoResponse {
+    let config = project.config();
+    let rounds = project.rounds().await.expect("can't get rounds");
+
+    let draft = match draft_issue(
+        &config.github_repository,
+        rounds.iter().filter(|round| filter.matches(round)),
+        &query.fingerprint,
+    ) {
+        Some(draft) => draft,
+        None => return (StatusCode::NOT_FOUND, "runtime not found").into_response(),
+    };
+
+    let new_issue_url = reqwest::Url::parse_with_params(
+        &format!("https://github.com/{}/issues/new", config.github_repository),
+        [("title", &draft.title), ("body", &draft.body)],
+    )
+    .expect("couldn't build new issue url")
+    .to_string();
+
+    Json(IssueDraftResponse {
+        draft,
+        new_issue_url,
+    })
+    .into_response()
+}
+
+#[tracing::instrument]
+pub async fn create_issue_from_draft(
+    _: AdminAuth,
+    CurrentProject(project): CurrentProject,
+    Query(filter): Query<RoundFilter>,
+    Query(query): Query<IssueQuery>,
+) -> impl IntoResponse {
+    let config = project.config();
+
+    let github_token = match &config.github_token {
+        Some(github_token) => github_token,
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                "creating issues is disabled, set `github_token` to enable it",
+            )
+                .into_response()
+        }
+    };
+
+    let draft = {
+        let rounds = project.rounds().await.expect("can't get rounds");
+
+        match draft_issue(
+            &config.github_repository,
+            rounds.iter().filter(|round| filter.matches(round)),
+            &query.fingerprint,
+        ) {
+            Some(draft) => draft,
+            None => return (StatusCode::NOT_FOUND, "runtime not found").into_response(),
+        }
+    };
+
+    match create_issue(&config.github_repository, github_token, &draft).await {
+        Ok(created_issue) => (StatusCode::CREATED, Json(created_issue)).into_response(),
+
+        Err(error) => {
+            tracing::warn!("couldn't create issue\n{error:#}");
+            (
+                StatusCode::BAD_GATEWAY,
+                format!("couldn't create issue: {error:#}"),
+            )
+                .into_response()
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use axum::{
+    body::{boxed, Body, Bytes},
+    response::IntoResponse,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::routes::CurrentProject;
+
+// So proxies don't close the connection, and dead clients are noticed
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+fn event(name: &str, data: &[u8]) -> Bytes {
+    let mut event = format!("event: {name}\ndata: ").into_bytes();
+    event.extend_from_slice(data);
+    event.extend_from_slice(b"\n\n");
+    event.into()
+}
+
+/// `update` events after every refresh. After a `lagged` event or reconnecting, load everything again.
+#[tracing::instrument]
+pub async fn live(CurrentProject(project): CurrentProject) -> impl IntoResponse {
+    let mut receiver = project.live_updates.subscribe();
+    let (mut sender, body) = Body::channel();
+
+    tokio::task::spawn(async move {
+        let mut keep_alive = tokio::time::interval(KEEP_ALIVE);
+
+        loop {
+            let event = tokio::select! {
+                update = receiver.recv() => match update {
+                    Ok(update) => event("update", &update),
+
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::debug!("live client missed {missed} updates");
+                        event("lagged", missed.to_string().as_bytes())
+                    }
+
+                    Err(RecvError::Closed) => return,
+                },
+
+                _ = keep_alive.tick() => Bytes::from_static(b": keep-alive\n\n"),
+            };
+
+            if sender.send_data(event).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    (
+        [
+            ("content-type", "text/event-stream"),
+            ("cache-control", "no-cache"),
+        ],
+        boxed(body),
+    )
+}
@@ -0,0 +1,13 @@
+use std::sync::Arc;
+
+use axum::{response::IntoResponse, Extension};
+
+use crate::state::AppState;
+
+#[tracing::instrument]
+pub async fn metrics(Extension(state): Extension<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [("content-type", "text/plain; version=0.0.4")],
+        crate::metrics::render(&state),
+    )
+}
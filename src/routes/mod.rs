@@ -1,2 +1,50 @@
+mod admin;
+pub use admin::*;
+
+mod aggregate;
+pub use aggregate::*;
+
+mod blame;
+pub use blame::*;
+
+mod current_project;
+pub use current_project::*;
+
 mod data;
 pub use data::*;
+
+mod export;
+pub use export::*;
+
+mod feed;
+pub use feed::*;
+
+mod fix_verification;
+pub use fix_verification::*;
+
+mod issue;
+pub use issue::*;
+
+mod live;
+pub use live::*;
+
+mod metrics;
+pub use metrics::*;
+
+mod projects;
+pub use projects::*;
+
+mod revisions;
+pub use revisions::*;
+
+mod servers;
+pub use servers::*;
+
+mod status;
+pub use status::*;
+
+mod trends;
+pub use trends::*;
+
+mod triage;
+pub use triage::*;
@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+use axum::{response::IntoResponse, Extension, Json};
+use serde::Serialize;
+
+use crate::state::AppState;
+
+#[derive(Serialize)]
+struct Project {
+    name: String,
+    github_repository: String,
+}
+
+#[tracing::instrument]
+pub async fn projects(Extension(state): Extension<Arc<AppState>>) -> impl IntoResponse {
+    Json(
+        state
+            .projects
+            .iter()
+            .map(|project| Project {
+                name: project.config().name.clone(),
+                github_repository: project.config().github_repository.clone(),
+            })
+            .collect::<Vec<_>>(),
+    )
+}
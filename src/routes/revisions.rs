@@ -0,0 +1,40 @@
+use axum::{extract::Query, response::IntoResponse, Json};
+use serde::Deserialize;
+
+use crate::{
+    analytics::new_runtimes_per_revision,
+    filters::{RoundFilter, RuntimeFilter},
+    routes::CurrentProject,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct NewRuntimesQuery {
+    /// Only return revisions starting with this, so short hashes work.
+    revision: Option<String>,
+}
+
+#[tracing::instrument]
+pub async fn new_runtimes(
+    CurrentProject(project): CurrentProject,
+    Query(filter): Query<RoundFilter>,
+    Query(query): Query<NewRuntimesQuery>,
+    Query(runtime_filter): Query<RuntimeFilter>,
+) -> impl IntoResponse {
+    let hidden = runtime_filter.hidden_fingerprints(&project).await;
+    let rounds = project.rounds().await.expect("can't get rounds");
+
+    let mut revisions =
+        new_runtimes_per_revision(rounds.iter().filter(|round| filter.matches(round)));
+
+    if let Some(revision) = &query.revision {
+        revisions.retain(|revision_runtimes| revision_runtimes.revision.starts_with(revision));
+    }
+
+    for revision_runtimes in &mut revisions {
+        revision_runtimes
+            .new_runtimes
+            .retain(|runtime| !hidden.contains(&runtime.fingerprint));
+    }
+
+    Json(revisions)
+}
@@ -0,0 +1,8 @@
+use axum::{response::IntoResponse, Json};
+
+use crate::routes::CurrentProject;
+
+#[tracing::instrument]
+pub async fn servers(CurrentProject(project): CurrentProject) -> impl IntoResponse {
+    Json(project.config().servers.clone())
+}
@@ -0,0 +1,135 @@
+use std::{path::PathBuf, sync::Arc};
+
+use axum::{http::StatusCode, response::IntoResponse, Extension, Json};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use crate::{
+    refresh::RefreshStatus,
+    request::{github_rate_limit, GitHubRateLimit},
+    routes::CurrentProject,
+    state::{AppState, ProjectState},
+};
+
+/// How many refreshes can be missed before a project stops being ready.
+const MISSED_REFRESHES_BEFORE_UNREADY: u64 = 3;
+
+#[tracing::instrument]
+pub async fn healthz() -> impl IntoResponse {
+    "ok"
+}
+
+/// Ready once every project has loaded its rounds, and hasn't gone too long without refreshing them.
+#[tracing::instrument]
+pub async fn readyz(Extension(state): Extension<Arc<AppState>>) -> impl IntoResponse {
+    let now = chrono::Utc::now().naive_utc();
+    let max_staleness = chrono::Duration::seconds(
+        (state.config().rounds_cache_delay_secs * MISSED_REFRESHES_BEFORE_UNREADY) as i64,
+    );
+
+    let mut problems = Vec::new();
+
+    for project in &state.projects {
+        let config = project.config();
+        let last_success = project.refresh_status.lock().unwrap().last_success;
+
+        match last_success {
+            None => problems.push(format!("{} hasn't loaded rounds yet", config.name)),
+
+            // Mock data is only loaded once
+            Some(last_success)
+                if config.mock_runtimes_data.is_none() && now - last_success > max_staleness =>
+            {
+                problems.push(format!(
+                    "{} hasn't refreshed successfully since {last_success}",
+                    config.name
+                ))
+            }
+
+            Some(_) => {}
+        }
+    }
+
+    if problems.is_empty() {
+        (StatusCode::OK, "ready".to_owned())
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, problems.join("\n"))
+    }
+}
+
+#[derive(Serialize)]
+struct Status {
+    refresh: RefreshStatus,
+
+    /// None while a refresh is running, since the rounds are locked.
+    rounds: Option<usize>,
+    rounds_missing_runtimes: Option<usize>,
+
+    cache_files: usize,
+    cache_bytes: u64,
+
+    github_rate_limit: Option<GitHubRateLimit>,
+    now: NaiveDateTime,
+}
+
+#[tracing::instrument]
+pub async fn status(CurrentProject(project): CurrentProject) -> impl IntoResponse {
+    let refresh = project.refresh_status.lock().unwrap().clone();
+
+    let (rounds, rounds_missing_runtimes) = match project.try_rounds() {
+        Ok(Some(rounds)) => (
+            Some(rounds.len()),
+            Some(
+                rounds
+                    .iter()
+                    .filter(|round| round.missing_runtimes.is_some())
+                    .count(),
+            ),
+        ),
+
+        _ => (None, None),
+    };
+
+    let (cache_files, cache_bytes) = cache_size(&project).await;
+
+    Json(Status {
+        refresh,
+        rounds,
+        rounds_missing_runtimes,
+        cache_files,
+        cache_bytes,
+        github_rate_limit: github_rate_limit(),
+        now: chrono::Utc::now().naive_utc(),
+    })
+}
+
+/// Counts the files and bytes in the project's cache folder.
+async fn cache_size(project: &ProjectState) -> (usize, u64) {
+    let mut files = 0;
+    let mut bytes = 0;
+
+    let mut directories: Vec<PathBuf> = vec![project.config().cache_path("")];
+
+    while let Some(directory) = directories.pop() {
+        let mut entries = match tokio::fs::read_dir(&directory).await {
+            Ok(entries) => entries,
+            Err(error) => {
+                tracing::debug!("couldn't read `{}`\n{error}", directory.display());
+                continue;
+            }
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            match entry.metadata().await {
+                Ok(metadata) if metadata.is_dir() => directories.push(entry.path()),
+                Ok(metadata) => {
+                    files += 1;
+                    bytes += metadata.len();
+                }
+                Err(_) => {}
+            }
+        }
+    }
+
+    (files, bytes)
+}
@@ -0,0 +1,87 @@
+use axum::{extract::Query, http::StatusCode, response::IntoResponse, Json};
+use serde::Deserialize;
+
+use crate::{
+    analytics::{runtime_trends, TrendDirection},
+    filters::{RoundFilter, RuntimeFilter},
+    routes::CurrentProject,
+};
+
+const DEFAULT_RECENT_HOURS: i64 = 24;
+
+/// Rounds are only kept for a week, so anything longer would leave nothing to compare against.
+const MAX_RECENT_HOURS: i64 = 7 * 24;
+
+const DEFAULT_TRENDS_LIMIT: usize = 50;
+
+/// Every trend carries each round it happened in, so the whole list can be huge.
+const MAX_TRENDS_LIMIT: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct TrendsQuery {
+    fingerprint: Option<String>,
+
+    /// How far back from the latest round counts as "recent", compared against everything before.
+    recent_hours: Option<i64>,
+
+    #[serde(default)]
+    significant_only: bool,
+
+    /// How many runtimes to return, most frequent first.
+    limit: Option<usize>,
+}
+
+#[tracing::instrument]
+pub async fn trends(
+    CurrentProject(project): CurrentProject,
+    Query(filter): Query<RoundFilter>,
+    Query(query): Query<TrendsQuery>,
+    Query(runtime_filter): Query<RuntimeFilter>,
+) -> impl IntoResponse {
+    let recent_hours = query.recent_hours.unwrap_or(DEFAULT_RECENT_HOURS);
+
+    if !(1..=MAX_RECENT_HOURS).contains(&recent_hours) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("`recent_hours` must be between 1 and {MAX_RECENT_HOURS}"),
+        )
+            .into_response();
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_TRENDS_LIMIT);
+
+    if !(1..=MAX_TRENDS_LIMIT).contains(&limit) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("`limit` must be between 1 and {MAX_TRENDS_LIMIT}"),
+        )
+            .into_response();
+    }
+
+    let hidden = runtime_filter.hidden_fingerprints(&project).await;
+    let rounds = project.rounds().await.expect("can't get rounds");
+
+    let mut trends = runtime_trends(
+        rounds.iter().filter(|round| filter.matches(round)),
+        chrono::Duration::hours(recent_hours),
+    );
+
+    trends.retain(|trend| !hidden.contains(&trend.fingerprint));
+
+    if let Some(fingerprint) = &query.fingerprint {
+        trends.retain(|trend| &trend.fingerprint == fingerprint);
+    }
+
+    if query.significant_only {
+        trends.retain(|trend| {
+            matches!(
+                trend.change.direction,
+                TrendDirection::Increasing | TrendDirection::Decreasing
+            )
+        });
+    }
+
+    trends.truncate(limit);
+
+    Json(trends).into_response()
+}
@@ -0,0 +1,165 @@
+use axum::{extract::Query, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    filters::RoundFilter,
+    fix_verification::{check_fix, is_commit_sha, FixVerification},
+    routes::{AdminAuth, CurrentProject},
+    triage::{find_reappearance, Reappearance, Triage, TriageStatus},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct TriageQuery {
+    fingerprint: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TriageEntry<'a> {
+    fingerprint: &'a str,
+
+    #[serde(flatten)]
+    triage: &'a Triage,
+
+    reappeared: Option<Reappearance>,
+
+    fix_verification: Option<FixVerification>,
+}
+
+#[tracing::instrument]
+pub async fn triage(
+    CurrentProject(project): CurrentProject,
+    Query(query): Query<TriageQuery>,
+) -> impl IntoResponse {
+    // Copied so neither lock is held while fixes are checked
+    let entries: Vec<(String, Triage)> = project
+        .triage()
+        .await
+        .iter()
+        .filter(|(fingerprint, _)| match &query.fingerprint {
+            Some(query_fingerprint) => *fingerprint == query_fingerprint,
+            None => true,
+        })
+        .map(|(fingerprint, triage)| (fingerprint.clone(), triage.clone()))
+        .collect();
+
+    let mut fix_verifications = Vec::with_capacity(entries.len());
+
+    for (fingerprint, triage) in &entries {
+        fix_verifications.push(match (&triage.status, &triage.fixed_in) {
+            (TriageStatus::Fixed, Some(fixed_in)) => Some(
+                check_fix(&project, &RoundFilter::default(), fingerprint, fixed_in, 0)
+                    .await
+                    .expect("can't get rounds"),
+            ),
+
+            _ => None,
+        });
+    }
+
+    let rounds = project.rounds().await.expect("can't get rounds");
+
+    Json(
+        entries
+            .iter()
+            .zip(fix_verifications)
+            .map(|((fingerprint, triage), fix_verification)| TriageEntry {
+                fingerprint,
+                triage,
+                reappeared: find_reappearance(fingerprint, triage, rounds.iter()),
+                fix_verification,
+            })
+            .collect::<Vec<_>>(),
+    )
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TriageUpdate {
+    status: TriageStatus,
+    link: Option<String>,
+    note: Option<String>,
+    fixed_in: Option<String>,
+
+    // Everyone shares the admin token
+    set_by: String,
+}
+
+fn missing_fingerprint() -> axum::response::Response {
+    (StatusCode::BAD_REQUEST, "`fingerprint` is required").into_response()
+}
+
+#[tracing::instrument]
+pub async fn set_triage(
+    _: AdminAuth,
+    CurrentProject(project): CurrentProject,
+    Query(query): Query<TriageQuery>,
+    Json(update): Json<TriageUpdate>,
+) -> impl IntoResponse {
+    let fingerprint = match query.fingerprint {
+        Some(fingerprint) => fingerprint,
+        None => return missing_fingerprint(),
+    };
+
+    if let Some(fixed_in) = &update.fixed_in {
+        if !is_commit_sha(fixed_in) {
+            return (StatusCode::BAD_REQUEST, "`fixed_in` isn't a commit hash").into_response();
+        }
+    }
+
+    let triage = Triage {
+        status: update.status,
+        link: update.link,
+        note: update.note,
+        fixed_in: update.fixed_in,
+        set_by: update.set_by,
+        set_at: chrono::Utc::now().naive_utc(),
+    };
+
+    match project
+        .set_triage(fingerprint.clone(), Some(triage.clone()))
+        .await
+    {
+        Ok(_) => {
+            tracing::info!(
+                "{} triaged {fingerprint} as {:?}",
+                triage.set_by,
+                triage.status
+            );
+
+            Json(TriageEntry {
+                fingerprint: &fingerprint,
+                triage: &triage,
+                reappeared: None,
+                fix_verification: None,
+            })
+            .into_response()
+        }
+
+        Err(error) => {
+            tracing::error!("couldn't set triage\n{error:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("{error:#}")).into_response()
+        }
+    }
+}
+
+#[tracing::instrument]
+pub async fn clear_triage(
+    _: AdminAuth,
+    CurrentProject(project): CurrentProject,
+    Query(query): Query<TriageQuery>,
+) -> impl IntoResponse {
+    let fingerprint = match query.fingerprint {
+        Some(fingerprint) => fingerprint,
+        None => return missing_fingerprint(),
+    };
+
+    match project.set_triage(fingerprint, None).await {
+        Ok(Some(_)) => StatusCode::NO_CONTENT.into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "runtime isn't triaged").into_response(),
+
+        Err(error) => {
+            tracing::error!("couldn't clear triage\n{error:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("{error:#}")).into_response()
+        }
+    }
+}
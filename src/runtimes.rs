@@ -9,7 +9,7 @@ runtime error: (?P<exception>.+)
 proc name: (?P<proc>.+?) \((?P<proc_path>.+?)\)
   source file: (?P<source_file>.+?),(?P<line>[0-9]+)"#;
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
 pub struct RuntimeBatch {
     pub count: u64,
     pub exception: String,
@@ -20,13 +20,19 @@ pub struct RuntimeBatch {
     pub best_guess_filenames: Option<BestGuessFilenames>,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, PartialEq, Eq, Hash, Serialize)]
 pub enum BestGuessFilenames {
     Definitely(PathBuf),
     Possible(Vec<PathBuf>),
 }
 
 impl RuntimeBatch {
+    /// Identifies the same runtime across rounds. Must match `runtimeToKey` on the frontend.
+    // Not too specific in case of file changes
+    pub fn fingerprint(&self) -> String {
+        format!("{}_______{}", self.exception, self.proc_path)
+    }
+
     fn patch_special_procs(&mut self) {
         if self.proc_path == "/proc/_stack_trace" {
             self.patch_stack_trace();
@@ -113,6 +119,28 @@ pub fn get_runtimes_for_round(
         .collect()
 }
 
+#[cfg(test)]
+impl RuntimeBatch {
+    pub fn mock(exception: &str, proc_path: &str, count: u64) -> Self {
+        Self {
+            count,
+            exception: exception.to_owned(),
+            proc_path: proc_path.to_owned(),
+            source_file: "code.dm".to_owned(),
+            line: 1,
+            best_guess_filenames: None,
+        }
+    }
+
+    /// One of each, for tests that only care which runtimes happened where.
+    pub fn mock_each(exceptions: &[&str]) -> Vec<Self> {
+        exceptions
+            .iter()
+            .map(|exception| Self::mock(exception, "/proc/thing", 1))
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
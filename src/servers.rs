@@ -1,40 +1,45 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct Server {
-    pub name: &'static str,
+    pub name: String,
     pub port: u16,
+
+    /// Any CSS color, used to tell servers apart on the dashboard.
+    pub color: Option<String>,
+
+    /// The server's folder in the logs URL, if it's different from its name.
+    pub log_path: Option<String>,
 }
 
-pub type Servers = [Server; 6];
+impl Server {
+    fn new(name: &str, port: u16, color: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            port,
+            color: Some(color.to_owned()),
+            log_path: None,
+        }
+    }
 
-pub const SERVERS: Servers = [
-    Server {
-        name: "basil",
-        port: 2337,
-    },
-    Server {
-        name: "sybil",
-        port: 1337,
-    },
-    Server {
-        name: "terry",
-        port: 3336,
-    },
-    Server {
-        name: "manuel",
-        port: 1447,
-    },
-    Server {
-        name: "campbell",
-        port: 6337,
-    },
-    Server {
-        name: "event-hall-us",
-        port: 4447,
-    },
-];
+    pub fn log_path(&self) -> &str {
+        self.log_path.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Used when no servers are configured.
+pub fn default_servers() -> Vec<Server> {
+    vec![
+        Server::new("basil", 2337, "hsl(20, 100%, 15%)"),
+        Server::new("sybil", 1337, "hsl(245, 100%, 70%)"),
+        Server::new("terry", 3336, "hsl(0, 100%, 30%)"),
+        Server::new("manuel", 1447, "hsl(20, 100%, 50%)"),
+        Server::new("campbell", 6337, "hsl(275, 100%, 70%)"),
+        Server::new("event-hall-us", 4447, "hsl(100, 100%, 70%)"),
+    ]
+}
 
-pub fn server_by_port(port: u16) -> Option<&'static Server> {
-    SERVERS.iter().find(|s| s.port == port)
+pub fn server_by_port(servers: &[Server], port: u16) -> Option<&Server> {
+    servers.iter().find(|s| s.port == port)
 }
@@ -1,17 +1,36 @@
-use std::{collections::HashSet, fmt::Debug, ops::Deref, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    ops::Deref,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use color_eyre::eyre::Context;
-use sqlx::{Connection, Row};
+use axum::body::Bytes;
+use color_eyre::eyre::{Context, ContextCompat};
+use sqlx::{
+    mysql::{MySqlPool, MySqlPoolOptions},
+    Row,
+};
 use tokio::{
     io::AsyncReadExt,
-    sync::{RwLock, RwLockReadGuard},
+    sync::{broadcast, RwLock, RwLockReadGuard},
 };
 
-use crate::{config::Config, rounds::Round};
+use crate::{
+    compression::CompressedBody,
+    config::{Config, ProjectConfig},
+    dashboard::DataFormat,
+    live::{live_update, LIVE_UPDATE_BUFFER},
+    missing_runtimes::MissingRuntimes,
+    refresh::RefreshStatus,
+    rounds::Round,
+    triage::{read_triage, write_triage, Triage, TriageEntries},
+};
 
 pub struct AppState {
-    pub config: Config,
-    rounds: RwLock<Option<Vec<Round>>>,
+    config: std::sync::RwLock<Arc<Config>>,
+    pub projects: Vec<Arc<ProjectState>>,
 }
 
 impl Debug for AppState {
@@ -21,38 +40,293 @@ impl Debug for AppState {
 }
 
 impl AppState {
-    pub fn new(config: Config) -> Self {
-        Self {
-            config,
-            rounds: RwLock::new(None),
+    pub fn new(config: Config) -> color_eyre::Result<Self> {
+        Ok(Self {
+            projects: config
+                .projects()?
+                .into_iter()
+                .map(|project_config| ProjectState::new(project_config).map(Arc::new))
+                .collect::<color_eyre::Result<_>>()?,
+            config: std::sync::RwLock::new(Arc::new(config)),
+        })
+    }
+
+    pub fn config(&self) -> Arc<Config> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Applies what can be changed without restarting from a freshly read config,
+    /// and logs what can't.
+    pub fn reload_config(&self, new_config: Config) -> color_eyre::Result<()> {
+        let new_projects = new_config.projects()?;
+        let old_config = self.config();
+
+        let needs_restart = [
+            (
+                "address",
+                new_config.address != old_config.address,
+                "the server is already listening",
+            ),
+            (
+                "port",
+                new_config.port != old_config.port,
+                "the server is already listening",
+            ),
+        ];
+
+        for (key, changed, reason) in needs_restart {
+            if changed {
+                tracing::warn!("not changing `{key}`, since {reason}; restart to apply");
+            }
         }
+
+        let old_names: Vec<String> = self
+            .projects
+            .iter()
+            .map(|project| project.config().name.clone())
+            .collect();
+
+        let new_names: Vec<String> = new_projects
+            .iter()
+            .map(|project| project.name.clone())
+            .collect();
+
+        if old_names != new_names {
+            tracing::warn!(
+                "not adding, removing or reordering projects, since they're only set up on startup; restart to apply"
+            );
+        }
+
+        for project in &self.projects {
+            let name = project.config().name.clone();
+
+            if let Some(new_project) = new_projects.iter().find(|new| new.name == name) {
+                project.reload_config(new_project.clone());
+            }
+        }
+
+        if new_config.rounds_cache_delay_secs != old_config.rounds_cache_delay_secs {
+            tracing::info!(
+                "rounds_cache_delay_secs is now {}, starting after the current wait",
+                new_config.rounds_cache_delay_secs
+            );
+        }
+
+        // Everything else at the top level is either per project, handled above, or needs a restart
+        *self.config.write().unwrap() = Arc::new(Config {
+            rounds_cache_delay_secs: new_config.rounds_cache_delay_secs,
+            admin_token: new_config.admin_token,
+            public_url: new_config.public_url,
+            ..(*old_config).clone()
+        });
+
+        Ok(())
+    }
+
+    pub fn project(&self, name: &str) -> Option<&Arc<ProjectState>> {
+        self.projects
+            .iter()
+            .find(|project| project.config().name == name)
+    }
+
+    /// The project used by routes that aren't namespaced.
+    pub fn default_project(&self) -> &Arc<ProjectState> {
+        self.projects.first().expect("no projects configured")
     }
+}
+
+/// Schema revisions whose tables have every column we query, inclusive.
+/// The major version changes when columns are removed or renamed, so newer ones need checking first.
+const SUPPORTED_SCHEMA_REVISIONS: std::ops::RangeInclusive<(u32, u32)> = (5, 0)..=(5, u32::MAX);
+
+pub struct ProjectState {
+    /// Replaced when the config is reloaded, so take a copy with `config()` rather than holding onto it.
+    config: std::sync::RwLock<Arc<ProjectConfig>>,
+
+    /// None when rounds are discovered from logs instead.
+    pool: Option<MySqlPool>,
+    rounds: RwLock<Option<Vec<Round>>>,
 
-    async fn connect(&self) -> color_eyre::Result<sqlx::MySqlConnection> {
-        sqlx::MySqlConnection::connect(&self.config.db_url)
+    /// The last serialized `/data.json` in each format, and when it was made.
+    pub cached_data_responses:
+        Mutex<HashMap<DataFormat, (std::time::Instant, Arc<CompressedBody>)>>,
+
+    pub refresh_status: Mutex<RefreshStatus>,
+
+    /// Serialized `LiveUpdate`s, sent after every refresh that changed something.
+    pub live_updates: broadcast::Sender<Bytes>,
+
+    triage: RwLock<TriageEntries>,
+}
+
+impl Debug for ProjectState {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "ProjectState({})", self.config().name)
+    }
+}
+
+impl ProjectState {
+    pub fn new(config: ProjectConfig) -> color_eyre::Result<Self> {
+        // Connections are made when first needed, so a database that's down doesn't stop startup
+        let pool = match &config.db_url {
+            Some(db_url) => Some(
+                MySqlPoolOptions::new()
+                    .max_connections(config.database.max_connections)
+                    .acquire_timeout(Duration::from_secs(config.database.acquire_timeout_secs))
+                    .idle_timeout(Duration::from_secs(config.database.idle_timeout_secs))
+                    .connect_lazy(db_url)
+                    .with_context(|| format!("invalid `db_url` for {}", config.name))?,
+            ),
+
+            None => None,
+        };
+
+        let triage = read_triage(&config.triage_path())
+            .with_context(|| format!("couldn't load triage for {}", config.name))?;
+
+        Ok(Self {
+            config: std::sync::RwLock::new(Arc::new(config)),
+            pool,
+            rounds: RwLock::new(None),
+            cached_data_responses: Mutex::new(HashMap::new()),
+            refresh_status: Mutex::new(RefreshStatus::default()),
+            live_updates: broadcast::channel(LIVE_UPDATE_BUFFER).0,
+            triage: RwLock::new(triage),
+        })
+    }
+
+    pub async fn triage(&self) -> RwLockReadGuard<'_, TriageEntries> {
+        self.triage.read().await
+    }
+
+    /// Sets or clears (with None) the triage for a runtime and saves it, returning what it was before.
+    pub async fn set_triage(
+        &self,
+        fingerprint: String,
+        triage: Option<Triage>,
+    ) -> color_eyre::Result<Option<Triage>> {
+        let mut entries = self.triage.write().await;
+
+        // Only changed once it's saved, so a failed write doesn't leave it out of sync with the file
+        let mut new_entries = entries.clone();
+
+        let previous = match triage {
+            Some(triage) => new_entries.insert(fingerprint, triage),
+            None => new_entries.remove(&fingerprint),
+        };
+
+        write_triage(&self.config().triage_path(), &new_entries)
             .await
-            .context("couldn't connect to database")
+            .context("couldn't save triage")?;
+
+        *entries = new_entries;
+
+        Ok(previous)
+    }
+
+    pub fn config(&self) -> Arc<ProjectConfig> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Applies the changes that are safe while running. The rest are kept as they were, with a warning.
+    fn reload_config(&self, new_config: ProjectConfig) {
+        let old_config = self.config();
+        let name = &old_config.name;
+
+        let needs_restart = [
+            (
+                "github_repository",
+                old_config.github_repository != new_config.github_repository,
+                "cached rounds and test merges came from the old repository",
+            ),
+            (
+                "db_url",
+                old_config.db_url != new_config.db_url,
+                "database connections are set up on startup",
+            ),
+            (
+                "database",
+                old_config.database != new_config.database,
+                "database connections are set up on startup",
+            ),
+            (
+                "mock_runtimes_data",
+                old_config.mock_runtimes_data != new_config.mock_runtimes_data,
+                "whether rounds are refreshed is decided on startup",
+            ),
+        ];
+
+        for (key, changed, reason) in needs_restart {
+            if changed {
+                tracing::warn!("not changing `{key}` for {name}, since {reason}; restart to apply");
+            }
+        }
+
+        let reloaded_config = ProjectConfig {
+            servers: new_config.servers,
+            logs_url: new_config.logs_url,
+            logs_directory: new_config.logs_directory,
+            webhooks: new_config.webhooks,
+            github_token: new_config.github_token,
+            dashboard_url: new_config.dashboard_url,
+            ..(*old_config).clone()
+        };
+
+        if reloaded_config == *old_config {
+            tracing::debug!("no config changes to apply for {name}");
+            return;
+        }
+
+        tracing::info!("applied config changes for {name}");
+
+        *self.config.write().unwrap() = Arc::new(reloaded_config);
+    }
+
+    fn pool(&self) -> color_eyre::Result<&MySqlPool> {
+        self.pool.as_ref().context("no `db_url` configured")
     }
 
     pub async fn current_db_revision(&self) -> color_eyre::Result<(u32, u32)> {
-        let mut connection = self.connect().await?;
+        let row = sqlx::query(
+            "SELECT major, minor FROM schema_revision ORDER BY major DESC, minor DESC LIMIT 1",
+        )
+        .fetch_optional(self.pool()?)
+        .await
+        .context("couldn't get the schema revision")?
+        .context("schema_revision is empty")?;
 
-        sqlx::query("SELECT major, minor FROM schema_revision ORDER BY date DESC")
-            .fetch_all(&mut connection)
-            .await
-            .map(|rows| {
-                let row = rows.first().expect("couldn't find revision");
-                (row.get(0), row.get(1))
-            })
-            .map_err(|error| error.into())
+        Ok((row.try_get("major")?, row.try_get("minor")?))
+    }
+
+    /// Fails if the database's schema is one we don't know how to query.
+    pub async fn check_db_revision(&self) -> color_eyre::Result<()> {
+        let (major, minor) = self.current_db_revision().await?;
+
+        tracing::debug!(
+            "current db revision for {}: {major}.{minor}",
+            self.config().name
+        );
+
+        if !SUPPORTED_SCHEMA_REVISIONS.contains(&(major, minor)) {
+            let (min_major, min_minor) = SUPPORTED_SCHEMA_REVISIONS.start();
+            let (max_major, _) = SUPPORTED_SCHEMA_REVISIONS.end();
+
+            color_eyre::eyre::bail!(
+                "database schema revision {major}.{minor} for {} isn't supported, \
+                expected at least {min_major}.{min_minor} and at most {max_major}.x",
+                self.config().name
+            );
+        }
+
+        Ok(())
     }
 
     pub fn try_rounds(&self) -> color_eyre::Result<Option<impl Deref<Target = Vec<Round>> + '_>> {
         match self.rounds.try_read() {
             Ok(rounds_lock) if rounds_lock.is_some() => {
-                return Ok(Some(RwLockReadGuard::map(rounds_lock, |rounds| {
+                Ok(Some(RwLockReadGuard::map(rounds_lock, |rounds| {
                     rounds.as_ref().expect("rounds is None")
-                })));
+                })))
             }
 
             Ok(_) => {
@@ -74,23 +348,43 @@ impl AppState {
     }
 
     pub async fn save_new_rounds(&self) -> color_eyre::Result<()> {
-        // Hold onto the lock for this entire time so nothing else tries to run DB queries
-        let mut rounds_write = self.rounds.write().await;
-
-        *rounds_write = Some(match self.load_rounds().await {
+        // Loaded without the lock, so requests are served the old rounds in the meantime.
+        // Only one refresh runs at a time, so nothing else is loading them too.
+        let rounds = match self.load_rounds().await {
             Ok(rounds) => rounds,
             Err(error) => {
                 tracing::error!("error loading rounds: {error}");
                 return Err(error);
             }
-        });
+        };
+
+        let previous_rounds = self.rounds.write().await.replace(rounds);
+
+        // Nothing to compare against on startup
+        if let Some(previous_rounds) = previous_rounds {
+            let rounds = self.rounds().await?;
+
+            crate::webhooks::notify(&self.config(), &previous_rounds, &rounds);
+
+            // Not worth working out if no one's listening
+            if self.live_updates.receiver_count() > 0 {
+                let update = live_update(&previous_rounds, &rounds);
+
+                if !update.is_empty() {
+                    let update = serde_json::to_vec(&update).expect("can't serialize live update");
+                    let _ = self.live_updates.send(update.into());
+                }
+            }
+        }
 
         Ok(())
     }
 
     #[tracing::instrument]
     async fn load_rounds(&self) -> color_eyre::Result<Vec<Round>> {
-        if let Some(mock_runtimes_data_filename) = &self.config.mock_runtimes_data {
+        let config = self.config();
+
+        if let Some(mock_runtimes_data_filename) = &config.mock_runtimes_data {
             match tokio::fs::File::open(&mock_runtimes_data_filename).await {
                 Ok(mut file) => {
                     tracing::debug!(
@@ -112,11 +406,17 @@ impl AppState {
             tracing::debug!("couldn't load from mock data, loading from cloud")
         }
 
-        let rounds = crate::rounds::load_rounds_over_cloud(&mut self.connect().await?)
-            .await
-            .context("couldn't load rounds over cloud")?;
+        let rounds = match config.db_url {
+            Some(_) => crate::rounds::load_rounds_over_cloud(self.pool()?, &config)
+                .await
+                .context("couldn't load rounds over cloud")?,
+
+            None => crate::rounds::load_rounds_from_logs(&config)
+                .await
+                .context("couldn't load rounds from logs")?,
+        };
 
-        if let Some(mock_runtimes_data_filename) = &self.config.mock_runtimes_data {
+        if let Some(mock_runtimes_data_filename) = &config.mock_runtimes_data {
             tracing::debug!(
                 "saving mock data to {}",
                 mock_runtimes_data_filename.display()
@@ -135,21 +435,31 @@ impl AppState {
     }
 
     async fn trash_old_cache(&self, rounds: &[Round]) {
+        let config = self.config();
         let mut used_files = HashSet::new();
 
         for round in rounds {
-            used_files.insert(PathBuf::from(format!(
-                "cache/rounds/{}.json",
-                round.round_id
-            )));
+            used_files.insert(config.cache_path(&format!("rounds/{}.json", round.round_id)));
+
+            used_files
+                .insert(config.cache_path(&format!("discovered_rounds/{}.json", round.round_id)));
+
+            if round.missing_runtimes.is_some() {
+                used_files.insert(MissingRuntimes::cache_file_path(&config, round.round_id));
+            }
 
             for test_merge in &round.test_merges {
-                used_files.insert(test_merge.cache_file_path());
+                used_files.insert(test_merge.cache_file_path(&config));
             }
         }
 
-        for parent in ["cache/rounds", "cache/test_merges"] {
-            if let Ok(mut entries) = tokio::fs::read_dir(parent).await {
+        for parent in [
+            "rounds",
+            "discovered_rounds",
+            "test_merges",
+            "missing_runtimes",
+        ] {
+            if let Ok(mut entries) = tokio::fs::read_dir(config.cache_path(parent)).await {
                 while let Ok(Some(entry)) = entries.next_entry().await {
                     if !used_files.contains(entry.path().as_path()) {
                         tracing::debug!("trashing old cache file {}", entry.path().display());
@@ -163,3 +473,87 @@ impl AppState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(
+        port: u16,
+        rounds_cache_delay_secs: u64,
+        admin_token: &str,
+        logs_directory: &str,
+    ) -> Config {
+        toml::from_str(&format!(
+            r#"
+            address = "127.0.0.1"
+            port = {port}
+            rounds_cache_delay_secs = {rounds_cache_delay_secs}
+            admin_token = "{admin_token}"
+            logs_directory = "{logs_directory}"
+            "#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reload_config() {
+        let state = AppState::new(config(2223, 600, "old", "/srv/old")).unwrap();
+
+        state
+            .reload_config(config(80, 60, "new", "/srv/new"))
+            .unwrap();
+
+        let config = state.config();
+        assert_eq!(config.rounds_cache_delay_secs, 60);
+        assert_eq!(config.port, 2223);
+        assert_eq!(config.admin_token.as_deref(), Some("new"));
+
+        assert_eq!(
+            state.default_project().config().logs_directory.as_deref(),
+            Some("/srv/new")
+        );
+    }
+
+    #[test]
+    fn test_reload_feed_urls() {
+        let config = |public_url: &str| -> Config {
+            toml::from_str(&format!(
+                r#"
+                address = "127.0.0.1"
+                port = 2223
+                rounds_cache_delay_secs = 600
+                public_url = "{public_url}"
+
+                [[projects]]
+                name = "tgstation"
+                logs_directory = "/srv/tgstation"
+
+                [[projects]]
+                name = "other"
+                logs_directory = "/srv/other"
+                dashboard_url = "{public_url}/other"
+                "#
+            ))
+            .unwrap()
+        };
+
+        let state = AppState::new(config("https://old.example")).unwrap();
+
+        state.reload_config(config("https://new.example")).unwrap();
+
+        assert_eq!(
+            state.config().public_url.as_deref(),
+            Some("https://new.example")
+        );
+        assert_eq!(
+            state
+                .project("other")
+                .unwrap()
+                .config()
+                .dashboard_url
+                .as_deref(),
+            Some("https://new.example/other")
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
+
+use chrono::NaiveDateTime;
+use color_eyre::eyre::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::rounds::{Round, RoundId};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriageStatus {
+    Acknowledged,
+
+    Ignored,
+
+    /// Flagged if it happens again on a newer revision.
+    Fixed,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Triage {
+    pub status: TriageStatus,
+
+    pub link: Option<String>,
+    pub note: Option<String>,
+
+    #[serde(default)]
+    pub fixed_in: Option<String>,
+
+    pub set_by: String,
+    pub set_at: NaiveDateTime,
+}
+
+pub type TriageEntries = BTreeMap<String, Triage>;
+
+pub fn read_triage(path: &Path) -> color_eyre::Result<TriageEntries> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .with_context(|| format!("couldn't parse `{}`", path.display())),
+
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(TriageEntries::new()),
+
+        Err(error) => Err(error).with_context(|| format!("couldn't read `{}`", path.display())),
+    }
+}
+
+// Through a temporary file, so a crash can't leave it half written
+pub async fn write_triage(path: &Path, entries: &TriageEntries) -> color_eyre::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let temporary_path = path.with_extension("json.tmp");
+
+    tokio::fs::write(&temporary_path, serde_json::to_string_pretty(entries)?).await?;
+    tokio::fs::rename(&temporary_path, path).await?;
+
+    Ok(())
+}
+
+pub fn ignored_fingerprints(entries: &TriageEntries) -> HashSet<&str> {
+    entries
+        .iter()
+        .filter(|(_, triage)| triage.status == TriageStatus::Ignored)
+        .map(|(fingerprint, _)| fingerprint.as_str())
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Reappearance {
+    pub round_id: RoundId,
+    pub server: String,
+    pub revision: String,
+    pub timestamp: NaiveDateTime,
+    pub count: u64,
+}
+
+/// The first round since it was marked fixed with the runtime, on a revision that wasn't running before then.
+pub fn find_reappearance<'a>(
+    fingerprint: &str,
+    triage: &Triage,
+    rounds: impl IntoIterator<Item = &'a Round>,
+) -> Option<Reappearance> {
+    if triage.status != TriageStatus::Fixed {
+        return None;
+    }
+
+    let rounds: Vec<&Round> = rounds.into_iter().collect();
+
+    let old_revisions: HashSet<&str> = rounds
+        .iter()
+        .filter(|round| round.timestamp <= triage.set_at)
+        .map(|round| round.revision.as_str())
+        .collect();
+
+    rounds
+        .iter()
+        .filter(|round| {
+            round.timestamp > triage.set_at && !old_revisions.contains(round.revision.as_str())
+        })
+        .filter_map(|round| {
+            let count = round
+                .runtimes
+                .as_ref()?
+                .iter()
+                .filter(|runtime| runtime.fingerprint() == fingerprint)
+                .map(|runtime| runtime.count)
+                .sum::<u64>();
+
+            (count > 0).then(|| Reappearance {
+                round_id: round.round_id,
+                server: round.server.clone(),
+                revision: round.revision.clone(),
+                timestamp: round.timestamp,
+                count,
+            })
+        })
+        .min_by_key(|reappearance| (reappearance.timestamp, reappearance.round_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtimes::RuntimeBatch;
+
+    #[test]
+    fn test_find_reappearance() {
+        let fingerprint = "Cannot read null.len_______/proc/broken";
+        let broken = || {
+            vec![RuntimeBatch::mock(
+                "Cannot read null.len",
+                "/proc/broken",
+                2,
+            )]
+        };
+
+        let before_fix = Round::mock_with_revision(1, "2022-10-01 00:00:00", "old", broken());
+
+        // Still running the old revision, so this is expected
+        let old_revision = Round::mock_with_revision(2, "2022-10-02 00:00:00", "old", broken());
+
+        let fixed = Round::mock_with_revision(3, "2022-10-02 01:00:00", "new", Vec::new());
+        let reappeared = Round::mock_with_revision(4, "2022-10-02 02:00:00", "new", broken());
+
+        let rounds = [reappeared, fixed, old_revision, before_fix];
+
+        let mut triage = Triage {
+            status: TriageStatus::Fixed,
+            link: None,
+            note: None,
+            fixed_in: None,
+            set_by: "someone".to_owned(),
+            set_at: NaiveDateTime::parse_from_str("2022-10-01 12:00:00", "%Y-%m-%d %H:%M:%S")
+                .unwrap(),
+        };
+
+        assert_eq!(
+            find_reappearance(fingerprint, &triage, &rounds),
+            Some(Reappearance {
+                round_id: 4,
+                server: "sybil".to_owned(),
+                revision: "new".to_owned(),
+                timestamp: rounds[0].timestamp,
+                count: 2,
+            })
+        );
+
+        assert_eq!(find_reappearance(fingerprint, &triage, &rounds[1..]), None);
+
+        triage.status = TriageStatus::Acknowledged;
+        assert_eq!(find_reappearance(fingerprint, &triage, &rounds), None);
+    }
+}
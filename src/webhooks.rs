@@ -0,0 +1,317 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    analytics::{diff_runtimes, RuntimeChange, RuntimeDiff, DEFAULT_SPIKE_RATIO},
+    config::ProjectConfig,
+    request::post_json,
+    rounds::Round,
+};
+
+/// Discord only shows so much of an embed, so longer lists are cut off.
+const DISCORD_RUNTIMES_PER_EMBED: usize = 10;
+const DISCORD_MAX_EXCEPTION_LENGTH: usize = 200;
+
+const DISCORD_NEW_COLOR: u32 = 0xe74c3c;
+const DISCORD_SPIKING_COLOR: u32 = 0xe67e22;
+const DISCORD_DISAPPEARED_COLOR: u32 = 0x2ecc71;
+
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+
+    #[serde(default)]
+    pub format: WebhookFormat,
+
+    /// How many times its usual rate per round a runtime has to hit to be reported as spiking.
+    #[serde(default = "default_spike_ratio")]
+    pub spike_ratio: f64,
+}
+
+fn default_spike_ratio() -> f64 {
+    DEFAULT_SPIKE_RATIO
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// The diff as is.
+    #[default]
+    Json,
+
+    /// Embeds that Discord can show, for pasting a channel's webhook URL straight in.
+    Discord,
+}
+
+#[derive(Serialize)]
+struct JsonPayload<'a> {
+    project: &'a str,
+
+    #[serde(flatten)]
+    diff: &'a RuntimeDiff,
+}
+
+/// Compares the rounds from before and after a refresh, and sends whatever changed to every webhook.
+/// Sending happens in the background, so a slow webhook doesn't hold up the refresh.
+pub fn notify(project: &ProjectConfig, previous: &[Round], current: &[Round]) {
+    for webhook in &project.webhooks {
+        let diff = diff_runtimes(previous, current, webhook.spike_ratio);
+
+        if diff.is_empty() {
+            continue;
+        }
+
+        let payload = build_payload(&project.name, &diff, webhook.format);
+        let url = webhook.url.clone();
+        let project_name = project.name.clone();
+
+        tokio::task::spawn(async move {
+            if let Err(error) = send(&url, &payload).await {
+                tracing::warn!("couldn't send webhook for {project_name}\n{error:#}");
+                crate::metrics::record_fetch_error(&project_name, "webhook");
+            }
+        });
+    }
+}
+
+fn build_payload(project: &str, diff: &RuntimeDiff, format: WebhookFormat) -> serde_json::Value {
+    match format {
+        WebhookFormat::Json => serde_json::to_value(JsonPayload { project, diff })
+            .expect("couldn't serialize webhook payload"),
+
+        WebhookFormat::Discord => discord_payload(project, diff),
+    }
+}
+
+fn discord_runtime_line(runtime: &RuntimeChange) -> String {
+    let mut exception = runtime.exception.clone();
+
+    if exception.chars().count() > DISCORD_MAX_EXCEPTION_LENGTH {
+        exception = exception
+            .chars()
+            .take(DISCORD_MAX_EXCEPTION_LENGTH)
+            .collect::<String>()
+            + "…";
+    }
+
+    format!(
+        "**{}** in `{}` ({}:{}), {} over {}",
+        exception.replace('*', "\\*"),
+        runtime.proc_path,
+        runtime.source_file,
+        runtime.line,
+        count(runtime.count, "time", "times"),
+        count(runtime.rounds as u64, "round", "rounds"),
+    )
+}
+
+/// Such as "1 new runtime" or "3 new runtimes".
+fn count(count: u64, singular: &str, plural: &str) -> String {
+    format!("{count} {}", if count == 1 { singular } else { plural })
+}
+
+fn discord_embed(title: String, color: u32, lines: Vec<String>) -> serde_json::Value {
+    let total = lines.len();
+    let mut lines: Vec<String> = lines.into_iter().take(DISCORD_RUNTIMES_PER_EMBED).collect();
+
+    if total > lines.len() {
+        lines.push(format!("…and {} more", total - lines.len()));
+    }
+
+    json!({
+        "title": title,
+        "color": color,
+        "description": lines.join("\n"),
+    })
+}
+
+fn discord_payload(project: &str, diff: &RuntimeDiff) -> serde_json::Value {
+    let mut embeds = Vec::new();
+
+    if !diff.new_runtimes.is_empty() {
+        embeds.push(discord_embed(
+            format!(
+                "{} on {project}",
+                count(
+                    diff.new_runtimes.len() as u64,
+                    "new runtime",
+                    "new runtimes"
+                )
+            ),
+            DISCORD_NEW_COLOR,
+            diff.new_runtimes.iter().map(discord_runtime_line).collect(),
+        ));
+    }
+
+    if !diff.spiking_runtimes.is_empty() {
+        embeds.push(discord_embed(
+            format!(
+                "{} on {project}",
+                count(
+                    diff.spiking_runtimes.len() as u64,
+                    "spiking runtime",
+                    "spiking runtimes"
+                )
+            ),
+            DISCORD_SPIKING_COLOR,
+            diff.spiking_runtimes
+                .iter()
+                .map(|spike| {
+                    format!(
+                        "{}, up from {:.1} to {:.1} per round",
+                        discord_runtime_line(&spike.runtime),
+                        spike.previous_per_round,
+                        spike.current_per_round,
+                    )
+                })
+                .collect(),
+        ));
+    }
+
+    if !diff.disappeared_runtimes.is_empty() {
+        embeds.push(discord_embed(
+            format!(
+                "{} disappeared from {project}",
+                count(
+                    diff.disappeared_runtimes.len() as u64,
+                    "runtime",
+                    "runtimes"
+                )
+            ),
+            DISCORD_DISAPPEARED_COLOR,
+            diff.disappeared_runtimes
+                .iter()
+                .map(discord_runtime_line)
+                .collect(),
+        ));
+    }
+
+    json!({
+        "username": "moth-fans-runtimes",
+        "embeds": embeds,
+    })
+}
+
+async fn send(url: &str, payload: &serde_json::Value) -> color_eyre::Result<()> {
+    post_json(url, None, payload).await?.error_for_status()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{Extension, Json, Router};
+
+    use super::*;
+    use crate::runtimes::RuntimeBatch;
+
+    type Received = Arc<Mutex<Vec<serde_json::Value>>>;
+
+    async fn receive(
+        Extension(received): Extension<Received>,
+        Json(payload): Json<serde_json::Value>,
+    ) {
+        received.lock().unwrap().push(payload);
+    }
+
+    /// Starts a server that stands in for the webhook, recording what's posted to it.
+    fn stand_in_server() -> (SocketAddr, Received) {
+        let received = Received::default();
+
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(
+            Router::new()
+                .route("/webhook", axum::routing::post(receive))
+                .layer(Extension(received.clone()))
+                .into_make_service(),
+        );
+
+        let address = server.local_addr();
+        tokio::task::spawn(server);
+
+        (address, received)
+    }
+
+    fn diff() -> RuntimeDiff {
+        let previous = vec![Round::mock(
+            1,
+            "2022-10-01 00:00:00",
+            vec![RuntimeBatch::mock("going away", "/proc/a", 4)],
+        )];
+
+        let current = vec![Round::mock(
+            2,
+            "2022-10-01 01:00:00",
+            vec![RuntimeBatch::mock("brand *new*", "/proc/b", 1)],
+        )];
+
+        diff_runtimes(&previous, &current, 3.0)
+    }
+
+    #[tokio::test]
+    async fn test_send_webhooks() {
+        let (address, received) = stand_in_server();
+        let url = format!("http://{address}/webhook");
+
+        let diff = diff();
+
+        send(
+            &url,
+            &build_payload("tgstation", &diff, WebhookFormat::Json),
+        )
+        .await
+        .unwrap();
+
+        send(
+            &url,
+            &build_payload("tgstation", &diff, WebhookFormat::Discord),
+        )
+        .await
+        .unwrap();
+
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 2);
+
+        let json = &received[0];
+        assert_eq!(json["project"], "tgstation");
+        assert_eq!(json["new_runtimes"][0]["exception"], "brand *new*");
+        assert_eq!(json["new_runtimes"][0]["count"], 1);
+        assert_eq!(json["disappeared_runtimes"][0]["exception"], "going away");
+        assert_eq!(json["spiking_runtimes"], json!([]));
+
+        let discord = &received[1];
+        assert_eq!(discord["embeds"].as_array().unwrap().len(), 2);
+        assert_eq!(discord["embeds"][0]["title"], "1 new runtime on tgstation");
+        assert_eq!(
+            discord["embeds"][0]["description"],
+            "**brand \\*new\\*** in `/proc/b` (code.dm:1), 1 time over 1 round"
+        );
+        assert_eq!(
+            discord["embeds"][1]["title"],
+            "1 runtime disappeared from tgstation"
+        );
+
+        assert!(send(&format!("http://{address}/missing"), json)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_discord_embed_truncates() {
+        let embed = discord_embed(
+            "title".to_owned(),
+            0,
+            (0..15).map(|number| number.to_string()).collect(),
+        );
+
+        assert_eq!(
+            embed["description"],
+            "0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n…and 5 more"
+        );
+    }
+}